@@ -1,16 +1,26 @@
-use crate::ui::{EnvVariables, InterruptSignal};
-use anyhow::{bail, Result};
+use crate::{
+    config::Shell,
+    ui::{EnvVariables, InterruptSignal},
+};
+use anyhow::{bail, Context, Result};
+use futures::{
+    future::{FutureExt, Shared},
+    StreamExt,
+};
 use std::{
     borrow::Cow,
     collections::HashMap,
+    env, fmt,
     ops::Deref,
-    process::{ExitStatus, Stdio},
+    path::{Path, PathBuf},
+    process::{Command as StdCommand, ExitStatus, Stdio},
     sync::Arc,
     time::Duration,
 };
-use tokio::process::Command as TokioCommand;
-use tokio::sync::mpsc::Receiver;
-use tokio::{io::AsyncReadExt, sync::Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command as TokioCommand};
+use tokio::sync::{mpsc::Receiver, oneshot, Mutex};
+use tokio::task::JoinHandle;
 
 // Type-States
 
@@ -37,6 +47,56 @@ pub struct Interruptible {
     pub interrupt_rx: Receiver<InterruptSignal>,
 }
 
+/// The settings that apply to the execution of all commands.
+#[derive(Clone)]
+pub struct CommandSettings {
+    pub shell: Shell,
+    /// Blocking commands that take longer are terminated.
+    pub timeout: Option<Duration>,
+    /// How long to wait for terminated commands to exit, before killing them.
+    pub grace_period: Duration,
+    pub working_dir: WorkingDir,
+    /// How many executions of `exec-each` operations run at the same time.
+    pub exec_each_concurrency: usize,
+}
+
+/// The working directory of all commands. It is shared between all commands,
+/// so that changing it affects all future commands, including the watched
+/// command.
+#[derive(Clone, Default)]
+pub struct WorkingDir(Arc<std::sync::Mutex<Option<PathBuf>>>);
+
+impl WorkingDir {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self(Arc::new(std::sync::Mutex::new(dir)))
+    }
+
+    /// The working directory, or None if commands are executed in the
+    /// working directory of watchbind.
+    pub fn get(&self) -> Option<PathBuf> {
+        self.0.lock().expect("lock isn't poisoned").clone()
+    }
+
+    /// Change the working directory to the given path, which may be relative
+    /// to the current working directory.
+    pub fn change(&self, path: &Path) -> Result<()> {
+        let mut dir = self.0.lock().expect("lock isn't poisoned");
+        let current_dir = match dir.as_ref() {
+            Some(dir) => dir.clone(),
+            None => env::current_dir().context("Failed to get the current directory")?,
+        };
+        let new_dir = current_dir
+            .join(path)
+            .canonicalize()
+            .with_context(|| format!("Failed to change directory to: {}", path.display()))?;
+        if !new_dir.is_dir() {
+            bail!("Not a directory: {}", new_dir.display());
+        }
+        *dir = Some(new_dir);
+        Ok(())
+    }
+}
+
 // Advantages of the Type-State Builder Pattern:
 // 1. We don't have any option/enum (an alternative configuration strategy)
 // checking overhead at runtime.
@@ -51,6 +111,7 @@ pub struct Interruptible {
 // #[derive(Clone)]
 pub struct CommandBuilder<B = NonBlocking, E = WithoutEnv, O = NoOutput, I = NonInterruptible> {
     command: String,
+    settings: CommandSettings,
     blocking: B,
     output: O,
     interruptible: I,
@@ -60,16 +121,11 @@ pub struct CommandBuilder<B = NonBlocking, E = WithoutEnv, O = NoOutput, I = Non
 
 // TODO: impl default trait so we don't have to duplicate this
 impl CommandBuilder {
-    pub fn new(command: String) -> Self {
-        let sh = ["sh", "-c", &command];
-        let mut process = TokioCommand::new(sh[0]);
-        process.args(&sh[1..]);
-        process.stdout(Stdio::null());
-        process.stderr(Stdio::null());
-
+    pub fn new(command: String, settings: CommandSettings) -> Self {
         CommandBuilder {
             // TODO: i think we don't even need command anymore, just the tokiocommand
             command,
+            settings,
             blocking: NonBlocking,
             output: NoOutput,
             interruptible: NonInterruptible,
@@ -82,18 +138,29 @@ impl CommandBuilder {
 /// Since we can't save a tokio::process::Command permanently and just clone it
 /// on new executions (it doesn't implement Clone), we store most of what's
 /// necessary to contruct it.
-#[derive(Default, Clone)]
+#[derive(Clone)]
 struct TokioCommandBuilder {
+    stdin: StdioClonable,
     stdout: StdioClonable,
     stderr: StdioClonable,
 }
 
+impl Default for TokioCommandBuilder {
+    fn default() -> Self {
+        Self {
+            stdin: StdioClonable::Inherit,
+            stdout: StdioClonable::Null,
+            stderr: StdioClonable::Null,
+        }
+    }
+}
+
 // TODO: this should be known at compile-time as well, not have a match statement
-#[derive(Default, Clone)]
+#[derive(Clone)]
 enum StdioClonable {
     Piped,
-    #[default]
     Null,
+    Inherit,
 }
 
 impl From<&StdioClonable> for Stdio {
@@ -101,6 +168,7 @@ impl From<&StdioClonable> for Stdio {
         match value {
             StdioClonable::Piped => Stdio::piped(),
             StdioClonable::Null => Stdio::null(),
+            StdioClonable::Inherit => Stdio::inherit(),
         }
     }
 }
@@ -114,6 +182,7 @@ impl<B, E, O, I> CommandBuilder<B, E, O, I> {
 
         CommandBuilder {
             command: self.command,
+            settings: self.settings,
             blocking: Blocking,
             output: self.output,
             interruptible: self.interruptible,
@@ -128,6 +197,7 @@ impl<B, E, O, I> CommandBuilder<B, E, O, I> {
     ) -> CommandBuilder<B, WithEnv, O, I> {
         CommandBuilder {
             command: self.command,
+            settings: self.settings,
             blocking: self.blocking,
             output: self.output,
             interruptible: self.interruptible,
@@ -142,6 +212,7 @@ impl<B, E, O, I> CommandBuilder<B, E, O, I> {
 
         CommandBuilder {
             command: self.command,
+            settings: self.settings,
             blocking: self.blocking,
             output: WithOutput,
             interruptible: self.interruptible,
@@ -150,12 +221,25 @@ impl<B, E, O, I> CommandBuilder<B, E, O, I> {
         }
     }
 
+    /// Pipe the input that is passed on execution into stdin.
+    pub fn with_stdin(mut self) -> Self {
+        self.tokio_command.stdin = StdioClonable::Piped;
+        self
+    }
+
+    /// Override the timeout of the command settings.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.settings.timeout = Some(timeout);
+        self
+    }
+
     pub fn interruptible(
         self,
         interrupt_rx: Receiver<InterruptSignal>,
     ) -> CommandBuilder<B, E, O, Interruptible> {
         CommandBuilder {
             command: self.command,
+            settings: self.settings,
             blocking: self.blocking,
             output: self.output,
             interruptible: Interruptible { interrupt_rx },
@@ -166,35 +250,313 @@ impl<B, E, O, I> CommandBuilder<B, E, O, I> {
 }
 
 impl<B, O, I> CommandBuilder<B, WithoutEnv, O, I> {
-    async fn create_shell_command(&self) -> TokioCommand {
+    async fn create_shell_command(&self) -> Result<TokioCommand> {
         // TODO: optimize: save ["sh", "-c", cmd] in hashmap to avoid reallocation
-        let sh = ["sh", "-c", &self.command];
+        let program_and_args = self.settings.shell.program_and_args(&self.command)?;
 
-        let mut command = TokioCommand::new(sh[0]);
+        let mut command = new_process_group_command(&program_and_args);
 
-        command.args(&sh[1..]);
+        command.stdin(&self.tokio_command.stdin);
         command.stdout(&self.tokio_command.stdout);
         command.stderr(&self.tokio_command.stderr);
+        if let Some(working_dir) = self.settings.working_dir.get() {
+            command.current_dir(working_dir);
+        }
 
-        command
+        Ok(command)
     }
 }
 
 impl<B, O, I> CommandBuilder<B, WithEnv, O, I> {
-    async fn create_shell_command(&self) -> TokioCommand {
+    async fn create_shell_command(&self) -> Result<TokioCommand> {
         // TODO: optimize: save ["sh", "-c", cmd] in hashmap to avoid reallocation
-        let sh = ["sh", "-c", &self.command];
+        let program_and_args = self.settings.shell.program_and_args(&self.command)?;
 
-        let mut command = TokioCommand::new(sh[0]);
+        let mut command = new_process_group_command(&program_and_args);
 
-        command.args(&sh[1..]);
+        command.stdin(&self.tokio_command.stdin);
         command.stdout(&self.tokio_command.stdout);
         command.stderr(&self.tokio_command.stderr);
+        if let Some(working_dir) = self.settings.working_dir.get() {
+            command.current_dir(working_dir);
+        }
 
         let env_variables: HashMap<_, _> = self.env.env_variables.lock().await.deref().into();
         command.envs(env_variables);
 
-        command
+        Ok(command)
+    }
+}
+
+/// Create a command that is spawned in its own process group, so that all
+/// processes it spawns (e.g. the commands of a pipeline) can be terminated
+/// together with it.
+fn new_process_group_command(program_and_args: &[String]) -> TokioCommand {
+    let mut command = StdCommand::new(&program_and_args[0]);
+    command.args(&program_and_args[1..]);
+
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    command.into()
+}
+
+/// A spawned command, which runs in its own process group together with all
+/// processes it spawns.
+pub struct ProcessGroup {
+    child: Child,
+    /// The id of the process group, which is the process id of the spawned
+    /// command. It has to be saved on spawn, because the child's id is
+    /// unavailable once it has been reaped.
+    id: Option<u32>,
+}
+
+impl ProcessGroup {
+    fn spawn(command: &mut TokioCommand) -> Result<Self> {
+        let child = command.spawn()?;
+        let id = child.id();
+        Ok(Self { child, id })
+    }
+
+    #[cfg(unix)]
+    fn pgid(&self) -> Option<nix::unistd::Pid> {
+        self.id.map(|id| nix::unistd::Pid::from_raw(id as i32))
+    }
+
+    /// Whether any process in the group is still running.
+    #[cfg(unix)]
+    fn is_running(&mut self) -> bool {
+        // Reap the spawned command if it has exited, because a zombie process
+        // still counts as a member of the process group.
+        let command_running = matches!(self.child.try_wait(), Ok(None));
+        match self.pgid() {
+            // Sending no signal only checks whether the group exists.
+            Some(pgid) => nix::sys::signal::killpg(pgid, None).is_ok(),
+            None => command_running,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Terminate all processes in the group: first gracefully with SIGTERM,
+    /// and, if any of them are still running after the grace period,
+    /// forcefully with SIGKILL.
+    #[cfg(unix)]
+    pub async fn terminate(&mut self, grace_period: Duration) -> Result<()> {
+        use nix::sys::signal::{killpg, Signal};
+
+        const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+        let Some(pgid) = self.pgid() else {
+            // The command has already been reaped.
+            return Ok(());
+        };
+
+        // Sending signals fails if all processes have already exited.
+        let _ = killpg(pgid, Signal::SIGTERM);
+        let deadline = tokio::time::Instant::now() + grace_period;
+        while self.is_running() {
+            if tokio::time::Instant::now() >= deadline {
+                let _ = killpg(pgid, Signal::SIGKILL);
+                break;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        self.child.wait().await?;
+
+        Ok(())
+    }
+
+    /// Wait for the command to exit. If it doesn't exit within the timeout of
+    /// the settings, its process group is terminated.
+    async fn wait(&mut self, settings: &CommandSettings, command: &str) -> Result<ExitStatus> {
+        let Some(timeout) = settings.timeout else {
+            return Ok(self.child.wait().await?);
+        };
+        match tokio::time::timeout(timeout, self.child.wait()).await {
+            Ok(exit_status) => Ok(exit_status?),
+            Err(_) => {
+                self.terminate(settings.grace_period).await?;
+                Err(TimedOut {
+                    command: command.to_owned(),
+                    timeout,
+                }
+                .into())
+            }
+        }
+    }
+
+    /// Wait for the command to exit, like [`Self::wait`], unless its
+    /// execution is cancelled first, in which case its process group is
+    /// terminated.
+    async fn wait_or_cancel(
+        &mut self,
+        settings: &CommandSettings,
+        command: &str,
+        cancel_signal: CancelSignal,
+    ) -> Result<ExitStatus> {
+        tokio::select! {
+            exit_status = self.wait(settings, command) => exit_status,
+            // Dropping the cancel handle cancels the execution as well.
+            _ = cancel_signal.0 => {
+                self.terminate(settings.grace_period).await?;
+                Err(Cancelled.into())
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub async fn terminate(&mut self, _grace_period: Duration) -> Result<()> {
+        self.child.kill().await?;
+        Ok(())
+    }
+}
+
+/// The error of a blocking command that didn't exit within its timeout, and
+/// was therefore terminated.
+#[derive(Debug)]
+pub struct TimedOut {
+    command: String,
+    timeout: Duration,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Command timed out after {:?}: {}",
+            self.timeout, self.command
+        )
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// The error of a blocking command whose execution was cancelled.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Command was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Cancels the execution of a blocking command, which then fails with a
+/// [`Cancelled`] error.
+#[derive(Debug)]
+pub struct CancelHandle(oneshot::Sender<()>);
+
+impl CancelHandle {
+    pub fn cancel(self) {
+        // The command might have already exited.
+        let _ = self.0.send(());
+    }
+}
+
+/// Received by a blocking command, whose execution is cancelled once the
+/// corresponding [`CancelHandle`] is used or dropped. Clones are cancelled
+/// together, e.g. all executions of an `exec-each` operation.
+#[derive(Clone)]
+pub struct CancelSignal(Shared<oneshot::Receiver<()>>);
+
+impl CancelSignal {
+    /// Whether the execution has been cancelled, as far as the signal has
+    /// been awaited so far.
+    fn is_cancelled(&self) -> bool {
+        self.0.peek().is_some()
+    }
+}
+
+pub fn cancel_channel() -> (CancelHandle, CancelSignal) {
+    let (tx, rx) = oneshot::channel();
+    (CancelHandle(tx), CancelSignal(rx.shared()))
+}
+
+/// The process groups of all background commands, as well as the background
+/// tasks that execute commands, which are terminated when watchbind exits.
+#[derive(Default)]
+pub struct BackgroundJobs {
+    process_groups: Vec<ProcessGroup>,
+    tasks: Vec<BackgroundTask>,
+}
+
+/// A background task that terminates the commands it executes once it is
+/// cancelled.
+struct BackgroundTask {
+    cancel_handle: CancelHandle,
+    join_handle: JoinHandle<()>,
+}
+
+impl BackgroundJobs {
+    pub fn add(&mut self, job: ProcessGroup) {
+        // Forget about all jobs that have exited in the meantime.
+        self.process_groups.retain_mut(ProcessGroup::is_running);
+        self.process_groups.push(job);
+    }
+
+    pub fn add_task(&mut self, cancel_handle: CancelHandle, join_handle: JoinHandle<()>) {
+        // Forget about all tasks that have finished in the meantime.
+        self.tasks.retain(|task| !task.join_handle.is_finished());
+        self.tasks.push(BackgroundTask {
+            cancel_handle,
+            join_handle,
+        });
+    }
+
+    /// Terminate all jobs and tasks concurrently, so they share the grace
+    /// period.
+    pub async fn terminate_all(self, grace_period: Duration) -> Result<()> {
+        let Self {
+            mut process_groups,
+            tasks,
+        } = self;
+        let join_handles = tasks.into_iter().map(|task| {
+            task.cancel_handle.cancel();
+            task.join_handle
+        });
+        let (terminated, _) = futures::join!(
+            futures::future::try_join_all(
+                process_groups
+                    .iter_mut()
+                    .map(|job| job.terminate(grace_period))
+            ),
+            futures::future::join_all(join_handles),
+        );
+        terminated?;
+        Ok(())
+    }
+}
+
+/// The results of executing a command once for every line.
+pub struct ExecEachSummary {
+    succeeded: usize,
+    /// The lines whose execution failed, along with the error.
+    failures: Vec<(String, anyhow::Error)>,
+}
+
+impl ExecEachSummary {
+    pub fn has_failures(&self) -> bool {
+        !self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for ExecEachSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.succeeded + self.failures.len();
+        if !self.has_failures() {
+            return write!(f, "All {} executions succeeded", total);
+        }
+        write!(f, "{} of {} executions failed:", self.failures.len(), total)?;
+        for (line, error) in &self.failures {
+            write!(f, "\n{}: {:#}", line, error)?;
+        }
+        Ok(())
     }
 }
 
@@ -202,8 +564,8 @@ impl<B, O, I> CommandBuilder<B, WithEnv, O, I> {
 // TODO: see where we can make it even more generic
 
 impl CommandBuilder<NonBlocking, WithoutEnv, NoOutput, NonInterruptible> {
-    pub async fn execute(&self) -> Result<()> {
-        self.create_shell_command().await.spawn()?;
+    pub async fn execute(&self) -> Result<ProcessGroup> {
+        let process_group = ProcessGroup::spawn(&mut self.create_shell_command().await?)?;
 
         // create_shell_command(&self.command)
         //     // We only need stderr in case of an error, stdout can be ignored
@@ -211,13 +573,13 @@ impl CommandBuilder<NonBlocking, WithoutEnv, NoOutput, NonInterruptible> {
         //     .stderr(Stdio::piped())
         //     .spawn()?;
 
-        Ok(())
+        Ok(process_group)
     }
 }
 
 impl CommandBuilder<NonBlocking, WithEnv, NoOutput, NonInterruptible> {
-    pub async fn execute(&self) -> Result<()> {
-        self.create_shell_command().await.spawn()?;
+    pub async fn execute(&self) -> Result<ProcessGroup> {
+        let process_group = ProcessGroup::spawn(&mut self.create_shell_command().await?)?;
 
         // let env_variables = self.env.env_variables.lock().await.deref().into();
         // create_shell_command_with_env(&self.command, env_variables)
@@ -226,13 +588,72 @@ impl CommandBuilder<NonBlocking, WithEnv, NoOutput, NonInterruptible> {
         //     .stderr(Stdio::piped())
         //     .spawn()?;
 
-        Ok(())
+        Ok(process_group)
     }
 }
 
 impl CommandBuilder<Blocking, WithEnv, NoOutput, NonInterruptible> {
-    pub async fn execute(&self) -> Result<()> {
-        let mut child = self.create_shell_command().await.spawn()?;
+    /// The input is written to stdin, if the command was built with
+    /// `with_stdin`.
+    pub async fn execute(&self, input: Option<String>, cancel_signal: CancelSignal) -> Result<()> {
+        let command = self.create_shell_command().await?;
+        self.execute_command(command, input, cancel_signal).await
+    }
+
+    /// Execute the command once for every line, with the env variable `line`
+    /// set to it, while running at most `exec_each_concurrency` executions
+    /// at the same time. Failed executions are collected in the summary,
+    /// while cancelling fails the whole execution.
+    pub async fn execute_each(
+        &self,
+        lines: Vec<String>,
+        cancel_signal: CancelSignal,
+    ) -> Result<ExecEachSummary> {
+        let results: Vec<(String, Result<()>)> = futures::stream::iter(lines)
+            // Don't start any further executions once cancelled.
+            .take_until(cancel_signal.0.clone())
+            .map(|line| {
+                let cancel_signal = cancel_signal.clone();
+                async move {
+                    let result = async {
+                        let mut command = self.create_shell_command().await?;
+                        command.env("line", &line);
+                        self.execute_command(command, None, cancel_signal).await
+                    }
+                    .await;
+                    (line, result)
+                }
+            })
+            .buffer_unordered(self.settings.exec_each_concurrency)
+            .collect()
+            .await;
+
+        if cancel_signal.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        let mut summary = ExecEachSummary {
+            succeeded: 0,
+            failures: vec![],
+        };
+        for (line, result) in results {
+            match result {
+                Ok(()) => summary.succeeded += 1,
+                Err(e) => summary.failures.push((line, e)),
+            }
+        }
+        Ok(summary)
+    }
+
+    async fn execute_command(
+        &self,
+        mut command: TokioCommand,
+        input: Option<String>,
+        cancel_signal: CancelSignal,
+    ) -> Result<()> {
+        let mut process_group = ProcessGroup::spawn(&mut command)?;
+        if let (Some(stdin), Some(input)) = (process_group.child.stdin.take(), input) {
+            write_stdin(stdin, input);
+        }
 
         // let env_variables = self.env.env_variables.lock().await.deref().into();
         // let mut child = create_shell_command_with_env(&self.command, env_variables)
@@ -241,7 +662,10 @@ impl CommandBuilder<Blocking, WithEnv, NoOutput, NonInterruptible> {
         //     .stderr(Stdio::piped())
         //     .spawn()?;
 
-        let exit_status = child.wait().await?;
+        let exit_status = process_group
+            .wait_or_cancel(&self.settings, &self.command, cancel_signal)
+            .await?;
+        let child = &mut process_group.child;
         assert_child_exited_successfully(exit_status, &mut child.stderr).await?;
 
         Ok(())
@@ -250,7 +674,7 @@ impl CommandBuilder<Blocking, WithEnv, NoOutput, NonInterruptible> {
 
 impl CommandBuilder<Blocking, WithoutEnv, WithOutput, NonInterruptible> {
     pub async fn execute(&self) -> Result<String> {
-        let mut child = self.create_shell_command().await.spawn()?;
+        let mut process_group = ProcessGroup::spawn(&mut self.create_shell_command().await?)?;
 
         // let mut child = create_shell_command(&self.command)
         //     // Keep both stdout and stderr
@@ -258,20 +682,26 @@ impl CommandBuilder<Blocking, WithoutEnv, WithOutput, NonInterruptible> {
         //     .stderr(Stdio::piped())
         //     .spawn()?;
 
-        let exit_status = child.wait().await?;
+        let exit_status = process_group.wait(&self.settings, &self.command).await?;
+        let child = &mut process_group.child;
         assert_child_exited_successfully(exit_status, &mut child.stderr).await?;
 
         // Read stdout
         let mut stdout = String::new();
-        child.stdout.unwrap().read_to_string(&mut stdout).await?;
+        child
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut stdout)
+            .await?;
 
         Ok(stdout)
     }
 }
 
 impl CommandBuilder<Blocking, WithEnv, WithOutput, NonInterruptible> {
-    pub async fn execute(&self) -> Result<String> {
-        let mut child = self.create_shell_command().await.spawn()?;
+    pub async fn execute(&self, cancel_signal: CancelSignal) -> Result<String> {
+        let mut process_group = ProcessGroup::spawn(&mut self.create_shell_command().await?)?;
 
         // let env_variables = self.env.env_variables.lock().await.deref().into();
         // let mut child = create_shell_command_with_env(&self.command, env_variables)
@@ -280,22 +710,48 @@ impl CommandBuilder<Blocking, WithEnv, WithOutput, NonInterruptible> {
         //     .stderr(Stdio::piped())
         //     .spawn()?;
 
-        let exit_status = child.wait().await?;
+        let exit_status = process_group
+            .wait_or_cancel(&self.settings, &self.command, cancel_signal)
+            .await?;
+        let child = &mut process_group.child;
         assert_child_exited_successfully(exit_status, &mut child.stderr).await?;
 
         // Read stdout
         let mut stdout = String::new();
-        child.stdout.unwrap().read_to_string(&mut stdout).await?;
+        child
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut stdout)
+            .await?;
 
         Ok(stdout)
     }
 }
 
+/// Write the input to stdin in the background, so that waiting for the
+/// command isn't blocked by a command that doesn't read all of its input.
+/// Stdin is closed afterwards, which signals the end of the input.
+fn write_stdin(mut stdin: ChildStdin, input: String) {
+    tokio::spawn(async move {
+        // The command might exit (or be terminated) before reading all of
+        // its input.
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+}
+
 /// Encodes whether a command's execution was interrupted, or the stdout if it
 /// ran to completion.
 pub enum ExecutionResult {
     Stdout(String),
+    /// The execution was interrupted to be reloaded.
     Interrupted,
+    /// The execution was interrupted to be cancelled, so it shouldn't be
+    /// reloaded.
+    Cancelled,
+    /// The execution was interrupted because the interrupt channel was
+    /// closed, so no further executions should be started.
+    ChannelClosed,
 }
 
 // TODO: find better name
@@ -307,7 +763,7 @@ pub enum WasWoken {
 
 impl CommandBuilder<Blocking, WithEnv, WithOutput, Interruptible> {
     pub async fn execute(&mut self) -> Result<ExecutionResult> {
-        let mut child = self.create_shell_command().await.spawn()?;
+        let mut process_group = ProcessGroup::spawn(&mut self.create_shell_command().await?)?;
 
         // let env_variables = self.env.env_variables.lock().await.deref().into();
 
@@ -318,17 +774,22 @@ impl CommandBuilder<Blocking, WithEnv, WithOutput, Interruptible> {
         //     .spawn()?;
 
         tokio::select! {
-            _ = self.interruptible.interrupt_rx.recv() => {
-                child.kill().await?;
-                Ok(ExecutionResult::Interrupted)
+            interrupt = self.interruptible.interrupt_rx.recv() => {
+                process_group.terminate(self.settings.grace_period).await?;
+                match interrupt {
+                    Some(InterruptSignal::Reload) => Ok(ExecutionResult::Interrupted),
+                    Some(InterruptSignal::Cancel) => Ok(ExecutionResult::Cancelled),
+                    None => Ok(ExecutionResult::ChannelClosed),
+                }
             },
-            exit_status = child.wait() => {
+            exit_status = process_group.wait(&self.settings, &self.command) => {
+                let child = &mut process_group.child;
                 assert_child_exited_successfully(exit_status?, &mut child.stderr).await?;
 
                 // Read stdout
                 let mut stdout = String::new();
                 // TODO: remove unwrap()
-                child.stdout.unwrap().read_to_string(&mut stdout).await?;
+                child.stdout.take().unwrap().read_to_string(&mut stdout).await?;
 
                 Ok(ExecutionResult::Stdout(stdout))
             }
@@ -337,19 +798,28 @@ impl CommandBuilder<Blocking, WithEnv, WithOutput, Interruptible> {
 }
 
 impl<B, E, O> CommandBuilder<B, E, O, Interruptible> {
-    /// Waits indefinitely for an interrupt signal.
+    /// Waits indefinitely for a reload signal. Cancel signals are ignored,
+    /// since there is no execution to cancel.
     pub async fn wait_for_interrupt(&mut self) -> WasWoken {
-        match self.interruptible.interrupt_rx.recv().await {
-            Some(InterruptSignal) => WasWoken::ReceivedInterrupt,
-            None => WasWoken::ChannelClosed,
+        loop {
+            match self.interruptible.interrupt_rx.recv().await {
+                Some(InterruptSignal::Reload) => return WasWoken::ReceivedInterrupt,
+                Some(InterruptSignal::Cancel) => {}
+                None => return WasWoken::ChannelClosed,
+            }
         }
     }
 
-    /// Waits for an interrupt signal up to a given timeout duration.
+    /// Waits for a reload signal up to a given timeout duration. Cancel
+    /// signals are ignored, since there is no execution to cancel.
     pub async fn wait_for_interrupt_within_timeout(&mut self, timeout: Duration) -> WasWoken {
-        match tokio::time::timeout(timeout, self.interruptible.interrupt_rx.recv()).await {
-            Ok(None) => WasWoken::ChannelClosed,
-            Ok(Some(InterruptSignal)) | Err(_) => WasWoken::ReceivedInterrupt,
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            match tokio::time::timeout_at(deadline, self.interruptible.interrupt_rx.recv()).await {
+                Ok(None) => return WasWoken::ChannelClosed,
+                Ok(Some(InterruptSignal::Cancel)) => {}
+                Ok(Some(InterruptSignal::Reload)) | Err(_) => return WasWoken::ReceivedInterrupt,
+            }
         }
     }
 }
@@ -386,34 +856,243 @@ async fn assert_child_exited_successfully(
     Ok(())
 }
 
-// TODO: update tests
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests {
-    // use super::*;
-
-    // #[test]
-    // fn test_executing_echo_command() -> Result<()> {
-    //     let (_, rx) = std::sync::mpsc::channel();
-    //     let echo_cmd = r#"echo "hello world""#.to_owned();
-    //     let command: Command = echo_cmd.parse()?;
-    //     let output_lines = command.capture_output(&rx)?;
-    //     assert_eq!(output_lines, "hello world\n");
-    //     Ok(())
-    // }
-
-    // #[test]
-    // fn test_multiline_output() -> Result<()> {
-    //     let (_, rx) = std::sync::mpsc::channel();
-    //     let cmd = r#"printf "one\ntwo\n""#.to_owned();
-    //     let command: Command = cmd.parse()?;
-    //     let output_lines = command.capture_output(&rx)?;
-    //     assert_eq!(output_lines, "one\ntwo\n");
-    //     Ok(())
-    // }
-
-    // TODO: can't add env AND capture output right now
-    // #[test]
-    // fn test_adding_lines_env_variable() -> Result<()> {
-    // 	Ok(())
-    // }
+    use super::*;
+    use crate::test_utils::{has_exited, is_running, read_pgid, sleeping_command, TempDir};
+    use itertools::Itertools;
+    use std::time::Instant;
+    use tokio::sync::mpsc;
+
+    fn settings() -> CommandSettings {
+        CommandSettings {
+            shell: "sh -c".parse().unwrap(),
+            timeout: None,
+            grace_period: Duration::from_secs(5),
+            working_dir: WorkingDir::default(),
+            exec_each_concurrency: 4,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_output() -> Result<()> {
+        let output = CommandBuilder::new(r#"printf "one\ntwo\n""#.to_owned(), settings())
+            .blocking()
+            .with_output()
+            .execute()
+            .await?;
+        assert_eq!(output, "one\ntwo\n");
+
+        let error = CommandBuilder::new("echo oops >&2; exit 3".to_owned(), settings())
+            .blocking()
+            .with_output()
+            .execute()
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("status code: 3"));
+        assert!(error.to_string().contains("oops"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_terminates_process_group() -> Result<()> {
+        let dir = TempDir::new("interrupt");
+        let pgid_file = dir.path().join("pgid");
+        let (interrupt_tx, interrupt_rx) = mpsc::channel(1);
+        let env_variables = Arc::new(Mutex::new(EnvVariables::default()));
+        let mut command = CommandBuilder::new(sleeping_command(&pgid_file), settings())
+            .blocking()
+            .with_output()
+            .with_env(env_variables)
+            .interruptible(interrupt_rx);
+
+        let (result, pgid) = tokio::join!(command.execute(), async {
+            let pgid = read_pgid(&pgid_file).await;
+            interrupt_tx.send(InterruptSignal::Reload).await.unwrap();
+            pgid
+        });
+        assert!(matches!(result?, ExecutionResult::Interrupted));
+        assert!(has_exited(pgid).await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_interrupt() -> Result<()> {
+        let dir = TempDir::new("cancel-interrupt");
+        let pgid_file = dir.path().join("pgid");
+        let (interrupt_tx, interrupt_rx) = mpsc::channel(1);
+        let env_variables = Arc::new(Mutex::new(EnvVariables::default()));
+        let mut command = CommandBuilder::new(sleeping_command(&pgid_file), settings())
+            .blocking()
+            .with_output()
+            .with_env(env_variables)
+            .interruptible(interrupt_rx);
+
+        let (result, pgid) = tokio::join!(command.execute(), async {
+            let pgid = read_pgid(&pgid_file).await;
+            interrupt_tx.send(InterruptSignal::Cancel).await.unwrap();
+            pgid
+        });
+        assert!(matches!(result?, ExecutionResult::Cancelled));
+        assert!(has_exited(pgid).await);
+
+        // Without an execution, there is nothing to cancel, so waiting
+        // continues until the channel is closed.
+        interrupt_tx.send(InterruptSignal::Cancel).await.unwrap();
+        drop(interrupt_tx);
+        assert!(matches!(
+            command.wait_for_interrupt().await,
+            WasWoken::ChannelClosed
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_terminate_background_jobs() -> Result<()> {
+        let dir = TempDir::new("background-jobs");
+        let pgid_file = dir.path().join("pgid");
+        let process_group = CommandBuilder::new(sleeping_command(&pgid_file), settings())
+            .execute()
+            .await?;
+        let pgid = read_pgid(&pgid_file).await;
+        assert_eq!(process_group.pgid(), Some(pgid));
+
+        let mut background_jobs = BackgroundJobs::default();
+        background_jobs.add(process_group);
+        assert!(is_running(pgid));
+        background_jobs
+            .terminate_all(settings().grace_period)
+            .await?;
+        assert!(has_exited(pgid).await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timeout() -> Result<()> {
+        let dir = TempDir::new("timeout");
+        let pgid_file = dir.path().join("pgid");
+        let env_variables = Arc::new(Mutex::new(EnvVariables::default()));
+        let command = format!("echo $$ > {}; sleep 5", pgid_file.display());
+        let (_cancel_handle, cancel_signal) = cancel_channel();
+
+        let start = Instant::now();
+        let error = CommandBuilder::new(command, settings())
+            .blocking()
+            .with_env(env_variables)
+            .with_timeout(Duration::from_millis(100))
+            .execute(None, cancel_signal)
+            .await
+            .unwrap_err();
+        assert!(error.is::<TimedOut>());
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert!(has_exited(read_pgid(&pgid_file).await).await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel() -> Result<()> {
+        let dir = TempDir::new("cancel");
+        let pgid_file = dir.path().join("pgid");
+        let env_variables = Arc::new(Mutex::new(EnvVariables::default()));
+        let command = CommandBuilder::new(sleeping_command(&pgid_file), settings())
+            .blocking()
+            .with_env(env_variables);
+        let (cancel_handle, cancel_signal) = cancel_channel();
+
+        let (result, pgid) = tokio::join!(command.execute(None, cancel_signal), async {
+            let pgid = read_pgid(&pgid_file).await;
+            cancel_handle.cancel();
+            pgid
+        });
+        assert!(result.unwrap_err().is::<Cancelled>());
+        assert!(has_exited(pgid).await);
+        Ok(())
+    }
+
+    fn execute_each(
+        command: &str,
+        concurrency: usize,
+        lines: &[&str],
+        cancel_signal: CancelSignal,
+    ) -> impl std::future::Future<Output = Result<ExecEachSummary>> {
+        let settings = CommandSettings {
+            exec_each_concurrency: concurrency,
+            ..settings()
+        };
+        let command = CommandBuilder::new(command.to_owned(), settings)
+            .blocking()
+            .with_env(Arc::new(Mutex::new(EnvVariables::default())));
+        let lines = lines.iter().map(|&line| line.to_owned()).collect();
+        async move { command.execute_each(lines, cancel_signal).await }
+    }
+
+    #[tokio::test]
+    async fn test_execute_each_summary() -> Result<()> {
+        let (_cancel_handle, cancel_signal) = cancel_channel();
+        let summary = execute_each("exit $line", 2, &["0", "1", "2"], cancel_signal).await?;
+        assert_eq!(summary.succeeded, 1);
+        let failed: Vec<&str> = summary
+            .failures
+            .iter()
+            .map(|(line, _)| &**line)
+            .sorted()
+            .collect();
+        assert_eq!(failed, ["1", "2"]);
+        assert!(summary.to_string().starts_with("2 of 3 executions failed:"));
+
+        let (_cancel_handle, cancel_signal) = cancel_channel();
+        let summary = execute_each("exit $line", 2, &["0", "0"], cancel_signal).await?;
+        assert!(!summary.has_failures());
+        assert_eq!(summary.to_string(), "All 2 executions succeeded");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_each_concurrency() -> Result<()> {
+        // Four executions of 0.2s take at least 0.4s, if at most two of them
+        // run at the same time.
+        let (_cancel_handle, cancel_signal) = cancel_channel();
+        let start = Instant::now();
+        let lines = ["a", "b", "c", "d"];
+        let summary = execute_each("sleep 0.2", 2, &lines, cancel_signal).await?;
+        assert_eq!(summary.succeeded, 4);
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_execute_each() -> Result<()> {
+        let dir = TempDir::new("cancel-exec-each");
+        let command = format!("echo $$ > {}/$line; sleep 100", dir.path().display());
+        let (cancel_handle, cancel_signal) = cancel_channel();
+
+        let execution = execute_each(&command, 1, &["a", "b"], cancel_signal);
+        let (result, pgid) = tokio::join!(execution, async {
+            let pgid = read_pgid(&dir.path().join("a")).await;
+            cancel_handle.cancel();
+            pgid
+        });
+        assert!(result.is_err_and(|e| e.is::<Cancelled>()));
+        assert!(has_exited(pgid).await);
+        // No further executions are started once cancelled.
+        assert!(!dir.path().join("b").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_grace_period_escalates_to_kill() -> Result<()> {
+        let dir = TempDir::new("grace-period");
+        let pgid_file = dir.path().join("pgid");
+        // Ignored signals are inherited, so no process exits on SIGTERM.
+        let command = format!("trap '' TERM; {}", sleeping_command(&pgid_file));
+        let mut process_group = CommandBuilder::new(command, settings()).execute().await?;
+        let pgid = read_pgid(&pgid_file).await;
+
+        let grace_period = Duration::from_millis(200);
+        let start = Instant::now();
+        process_group.terminate(grace_period).await?;
+        assert!(start.elapsed() >= grace_period);
+        assert!(has_exited(pgid).await);
+        Ok(())
+    }
 }
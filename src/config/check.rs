@@ -0,0 +1,793 @@
+use super::{
+    canonicalize_working_dir, check_exec_each_concurrency,
+    columns::TomlColumn,
+    keybindings::{KeyEvent, OperationParsed},
+    secs_to_duration,
+    style::parse_color,
+    Columns, Config, Fields, TomlConfig,
+};
+use anyhow::{bail, Result};
+use parse_display::Display;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::read_to_string,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use toml::Spanned;
+
+/// Keys whose values are colors.
+const COLOR_KEYS: [&str; 7] = [
+    "fg",
+    "bg",
+    "cursor-fg",
+    "cursor-bg",
+    "header-fg",
+    "header-bg",
+    "selected-bg",
+];
+
+/// Fully parse and validate the given config files (and the files they
+/// include), and print all problems found. Fails if any errors were found.
+pub fn check(config_files: &[PathBuf]) -> Result<()> {
+    let mut checker = Checker::default();
+    for config_file in config_files {
+        checker.check_file(config_file);
+    }
+
+    for diagnostic in &checker.diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    let count = |severity| {
+        checker
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    if errors > 0 {
+        bail!(
+            "Found {} error(s) and {} warning(s) in config files",
+            errors,
+            warnings
+        );
+    }
+    println!("No errors and {} warning(s) found", warnings);
+    Ok(())
+}
+
+#[derive(Display, PartialEq, Clone, Copy)]
+#[display(style = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a config file, optionally located at a line and column.
+struct Diagnostic {
+    severity: Severity,
+    file: PathBuf,
+    location: Option<(usize, usize)>,
+    message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some((line, column)) = self.location {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+#[derive(Default)]
+struct Checker {
+    diagnostics: Vec<Diagnostic>,
+    /// The canonical paths of all files that were already checked, so that
+    /// files included multiple times are only reported once.
+    checked_files: HashSet<PathBuf>,
+    /// The canonical paths of the files that are currently being checked,
+    /// i.e. the current file and all files including it, to detect cyclic
+    /// includes.
+    including_files: Vec<PathBuf>,
+}
+
+/// The config file that is currently being checked.
+struct File<'a> {
+    path: &'a Path,
+    /// The directory that relative paths in the file are relative to.
+    dir: &'a Path,
+    source: &'a str,
+}
+
+/// Whether a table is the top-level table of a config file or a profile.
+#[derive(PartialEq)]
+enum TableKind {
+    Root,
+    Profile,
+}
+
+impl Checker {
+    fn check_file(&mut self, path: &Path) {
+        let canonical_path = match path.canonicalize() {
+            Ok(canonical_path) => canonical_path,
+            Err(e) => {
+                self.report(Severity::Error, path, None, format!("Failed to read: {e}"));
+                return;
+            }
+        };
+        if !self.checked_files.insert(canonical_path.clone()) {
+            return;
+        }
+        let source = match read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                self.report(Severity::Error, path, None, format!("Failed to read: {e}"));
+                return;
+            }
+        };
+        let dir = canonical_path.parent().unwrap_or(Path::new(""));
+        let file = File {
+            path,
+            dir,
+            source: &source,
+        };
+        let errors_before = self.error_count();
+
+        let table: SpannedTable = match toml::from_str(&source) {
+            Ok(table) => table,
+            Err(e) => {
+                self.error(&file, e.span(), e.message().to_owned());
+                return;
+            }
+        };
+
+        let mut included_files = vec![];
+        self.check_table(&file, &table, TableKind::Root, &mut included_files);
+
+        self.including_files.push(canonical_path.clone());
+        for (included_file, span) in included_files {
+            let included_file = dir.join(included_file);
+            let is_cyclic = included_file
+                .canonicalize()
+                .is_ok_and(|canonical_path| self.including_files.contains(&canonical_path));
+            if is_cyclic {
+                self.error(
+                    &file,
+                    Some(span),
+                    format!("Cyclic include of config file {}", included_file.display()),
+                );
+            } else {
+                self.check_file(&included_file);
+            }
+        }
+        self.including_files.pop();
+
+        // Settings that are only invalid in combination (e.g. selecting
+        // fields by name without header lines) might be defined in different
+        // files, so they are checked in the merged config.
+        self.check_merged(&file, &table);
+
+        // Any other problems are only found when converting into the final
+        // config, which would report the errors found above a second time,
+        // though.
+        if self.error_count() == errors_before {
+            if let Err(e) = check_final(path) {
+                self.error(&file, None, format!("{e:#}"));
+            }
+        }
+    }
+
+    fn check_merged(&mut self, file: &File, table: &SpannedTable) {
+        // The merged config can't be parsed if any of the files is invalid,
+        // which was reported above.
+        let Ok(config) = TomlConfig::parse(file.path) else {
+            return;
+        };
+        let profiles: Vec<String> = config
+            .profiles
+            .iter()
+            .flatten()
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let fields_span = table.key_span("fields");
+        let error = check_combined_settings(config)
+            .err()
+            .map(|e| format!("{e:#}"));
+        if let Some(error) = &error {
+            self.error(file, fields_span.clone(), error.clone());
+        }
+        for profile in profiles {
+            let Ok(config) =
+                TomlConfig::parse(file.path).and_then(|config| config.select_profile(&profile))
+            else {
+                continue;
+            };
+            let Err(e) = check_combined_settings(config) else {
+                continue;
+            };
+            // Profiles often inherit the error, which is only reported once.
+            let profile_error = format!("{e:#}");
+            if error.as_ref() == Some(&profile_error) {
+                continue;
+            }
+            // Report the error at the profile's fields, or otherwise at the
+            // fields it inherits.
+            let span = table
+                .profile(&profile)
+                .and_then(|profile| profile.key_span("fields"))
+                .or(fields_span.clone());
+            self.error(
+                file,
+                span,
+                format!("Profile \"{profile}\": {profile_error}"),
+            );
+        }
+    }
+
+    fn check_table(
+        &mut self,
+        file: &File,
+        table: &SpannedTable,
+        kind: TableKind,
+        included_files: &mut Vec<(PathBuf, Range<usize>)>,
+    ) {
+        for (key, value) in &table.0 {
+            // Only keys are spanned, because values of implicit tables (e.g.
+            // `[profile.NAME]`) can't be, so entries are reported at their key.
+            let entry_span = key.span();
+            match key.get_ref().as_str() {
+                "include" | "extends" | "profile" if kind == TableKind::Profile => {
+                    self.error(
+                        file,
+                        Some(entry_span),
+                        "Profiles cannot contain profiles, includes or extends".to_owned(),
+                    );
+                    continue;
+                }
+                "profile" => {
+                    match value {
+                        SpannedValue::Table(profiles) => {
+                            for (name, profile) in &profiles.0 {
+                                match profile {
+                                    SpannedValue::Table(profile) => {
+                                        self.check_table(
+                                            file,
+                                            profile,
+                                            TableKind::Profile,
+                                            included_files,
+                                        );
+                                    }
+                                    _ => self.error(
+                                        file,
+                                        Some(name.span()),
+                                        format!("Profile \"{}\" must be a table", name.get_ref()),
+                                    ),
+                                }
+                            }
+                        }
+                        _ => self.error(
+                            file,
+                            Some(entry_span),
+                            "\"profile\" must be a table of profiles".to_owned(),
+                        ),
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Check the type of the value, using the same definition as when
+            // actually parsing the config.
+            let mut single_entry = toml::Table::new();
+            single_entry.insert(key.get_ref().clone(), value.to_value());
+            let config = match toml::Value::Table(single_entry).try_into::<TomlConfig>() {
+                Ok(config) => config,
+                Err(e) => {
+                    self.error(file, Some(entry_span), e.message().to_owned());
+                    continue;
+                }
+            };
+
+            match key.get_ref().as_str() {
+                "include" => included_files.extend(
+                    config
+                        .include
+                        .into_iter()
+                        .flatten()
+                        .zip(value.elements().iter().map(Spanned::span)),
+                ),
+                "extends" => included_files.extend(config.extends.zip(Some(entry_span))),
+                "working-dir" => {
+                    if let Some(working_dir) = config.working_dir {
+                        if let Err(e) = canonicalize_working_dir(file.dir.join(working_dir)) {
+                            self.error(file, Some(entry_span), format!("{e:#}"));
+                        }
+                    }
+                }
+                "interval" | "grace-period" | "command-timeout" => {
+                    let secs = config
+                        .interval
+                        .or(config.grace_period)
+                        .or(config.command_timeout)
+                        .unwrap_or_default();
+                    if let Err(e) = secs_to_duration(key.get_ref(), secs) {
+                        self.error(file, Some(entry_span), format!("{e:#}"));
+                    }
+                }
+                "exec-each-concurrency" => {
+                    let concurrency = config.exec_each_concurrency.unwrap_or_default();
+                    if let Err(e) = check_exec_each_concurrency(concurrency) {
+                        self.error(file, Some(entry_span), format!("{e:#}"));
+                    }
+                }
+                "keybindings" => self.check_keybindings(file, value),
+                "unbind" => {
+                    for key in value.elements() {
+                        self.check_key(file, key);
+                    }
+                }
+                "columns" => self.check_columns(file, value),
+                "cancel-key" => {
+                    let cancel_key = config.cancel_key.unwrap_or_default();
+                    if let Err(e) = cancel_key.parse::<KeyEvent>() {
+                        self.error(file, Some(entry_span), format!("{e:#}"));
+                    }
+                }
+                "initial-env" => {
+                    for operation in value.elements() {
+                        self.check_operation(file, operation);
+                    }
+                }
+                color_key if COLOR_KEYS.contains(&color_key) => {
+                    self.check_color(file, key.span(), value)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_keybindings(&mut self, file: &File, keybindings: &SpannedValue) {
+        let SpannedValue::Table(keybindings) = keybindings else {
+            return;
+        };
+        let default_keybindings = TomlConfig::default()
+            .keybindings
+            .expect("default keybindings exist");
+
+        for (key, operations) in &keybindings.0 {
+            match key.get_ref().parse::<KeyEvent>() {
+                Ok(key_event) => {
+                    if let Some(reason) = key_event.unreachable_reason() {
+                        self.warning(
+                            file,
+                            Some(key.span()),
+                            format!("Key \"{}\" can never be pressed: {}", key.get_ref(), reason),
+                        );
+                    }
+
+                    let default_operations = default_keybindings
+                        .as_ref()
+                        .iter()
+                        .find(|(default_key, _)| {
+                            default_key.parse::<KeyEvent>().ok().as_ref() == Some(&key_event)
+                        })
+                        .map(|(_, default_operations)| default_operations);
+                    // Unbinding a default keybinding is intentional.
+                    let unbound = operations.elements().is_empty();
+                    if let Some(default_operations) = default_operations.filter(|_| !unbound) {
+                        if operations.to_value() != default_operations.clone().into() {
+                            self.warning(
+                                file,
+                                Some(key.span()),
+                                format!(
+                                    "Key \"{}\" shadows the default keybinding: [ {} ]",
+                                    key.get_ref(),
+                                    default_operations
+                                        .iter()
+                                        .map(|operation| format!("\"{operation}\""))
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ),
+                            );
+                        }
+                    }
+                }
+                Err(e) => self.error(file, Some(key.span()), format!("{e:#}")),
+            }
+
+            for operation in operations.elements() {
+                self.check_operation(file, operation);
+            }
+        }
+    }
+
+    fn check_key(&mut self, file: &File, key: &Spanned<SpannedValue>) {
+        let SpannedValue::Other(toml::Value::String(key_str)) = key.get_ref() else {
+            return;
+        };
+        if let Err(e) = key_str.parse::<KeyEvent>() {
+            self.error(file, Some(key.span()), format!("{e:#}"));
+        }
+    }
+
+    fn check_operation(&mut self, file: &File, operation: &Spanned<SpannedValue>) {
+        let SpannedValue::Other(toml::Value::String(operation_str)) = operation.get_ref() else {
+            return;
+        };
+        if let Err(e) = OperationParsed::parse_explained(operation_str) {
+            self.error(file, Some(operation.span()), format!("{e:#}"));
+        }
+    }
+
+    fn check_columns(&mut self, file: &File, columns: &SpannedValue) {
+        for column in columns.elements() {
+            let Ok(column_toml) = column.get_ref().to_value().try_into::<TomlColumn>() else {
+                continue;
+            };
+            if let Err(e) = Columns::try_from(vec![column_toml]) {
+                self.error(file, Some(column.span()), format!("{e:#}"));
+            }
+        }
+    }
+
+    fn check_color(&mut self, file: &File, span: Range<usize>, color: &SpannedValue) {
+        if let SpannedValue::Other(toml::Value::String(color_str)) = color {
+            if let Err(e) = parse_color(Some(color_str.clone())) {
+                self.error(file, Some(span), format!("{e:#}"));
+            }
+        }
+    }
+
+    fn error(&mut self, file: &File, span: Option<Range<usize>>, message: String) {
+        let location = span.map(|span| line_and_column(file.source, span.start));
+        self.report(Severity::Error, file.path, location, message);
+    }
+
+    fn warning(&mut self, file: &File, span: Option<Range<usize>>, message: String) {
+        let location = span.map(|span| line_and_column(file.source, span.start));
+        self.report(Severity::Warning, file.path, location, message);
+    }
+
+    fn report(
+        &mut self,
+        severity: Severity,
+        file: &Path,
+        location: Option<(usize, usize)>,
+        message: String,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            file: file.to_path_buf(),
+            location,
+            message,
+        });
+    }
+
+    fn error_count(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .count()
+    }
+}
+
+/// Check the settings that are only invalid in combination with others.
+fn check_combined_settings(config: TomlConfig) -> Result<()> {
+    Fields::try_new(
+        config.field_separator,
+        config.field_selections,
+        config.header_lines.unwrap_or(0),
+    )?;
+    Ok(())
+}
+
+/// Parse the config file along with all files it includes, and convert it
+/// (and each of its profiles) into the final config.
+fn check_final(path: &Path) -> Result<()> {
+    let config = TomlConfig::parse(path)?;
+    let profiles: Vec<String> = config
+        .profiles
+        .iter()
+        .flatten()
+        .map(|(name, _)| name.clone())
+        .collect();
+    check_config(config)?;
+    for profile in profiles {
+        check_config(TomlConfig::parse(path)?.select_profile(&profile)?)?;
+    }
+    Ok(())
+}
+
+fn check_config(mut config: TomlConfig) -> Result<()> {
+    // The watched command might only be provided via command line.
+    config.watched_command.get_or_insert_with(String::new);
+    Config::try_from(config)?;
+    Ok(())
+}
+
+/// Convert a byte offset into a 1-based line and column.
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+    (line, column)
+}
+
+/// A TOML table that keeps the spans of all its (nested) keys and array
+/// elements.
+struct SpannedTable(Vec<(Spanned<String>, SpannedValue)>);
+
+impl SpannedTable {
+    fn get(&self, key: &str) -> Option<&SpannedValue> {
+        self.0
+            .iter()
+            .find(|(entry_key, _)| entry_key.get_ref() == key)
+            .map(|(_, value)| value)
+    }
+
+    fn key_span(&self, key: &str) -> Option<Range<usize>> {
+        self.0
+            .iter()
+            .find(|(entry_key, _)| entry_key.get_ref() == key)
+            .map(|(entry_key, _)| entry_key.span())
+    }
+
+    fn profile(&self, name: &str) -> Option<&SpannedTable> {
+        match self.get("profile")? {
+            SpannedValue::Table(profiles) => match profiles.get(name)? {
+                SpannedValue::Table(profile) => Some(profile),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+enum SpannedValue {
+    Table(SpannedTable),
+    Array(Vec<Spanned<SpannedValue>>),
+    Other(toml::Value),
+}
+
+impl SpannedValue {
+    /// Convert into a regular TOML value, dropping all spans.
+    fn to_value(&self) -> toml::Value {
+        match self {
+            Self::Table(table) => toml::Value::Table(
+                table
+                    .0
+                    .iter()
+                    .map(|(key, value)| (key.get_ref().clone(), value.to_value()))
+                    .collect(),
+            ),
+            Self::Array(array) => toml::Value::Array(
+                array
+                    .iter()
+                    .map(|value| value.get_ref().to_value())
+                    .collect(),
+            ),
+            Self::Other(value) => value.clone(),
+        }
+    }
+
+    /// The elements of an array, or nothing for any other value.
+    fn elements(&self) -> &[Spanned<SpannedValue>] {
+        match self {
+            Self::Array(array) => array,
+            _ => &[],
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SpannedTable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match SpannedValue::deserialize(deserializer)? {
+            SpannedValue::Table(table) => Ok(table),
+            _ => Err(de::Error::custom("expected a table")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SpannedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(SpannedValueVisitor)
+    }
+}
+
+struct SpannedValueVisitor;
+
+impl<'de> Visitor<'de> for SpannedValueVisitor {
+    type Value = SpannedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a TOML value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(SpannedValue::Other(value.into()))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(SpannedValue::Other(value.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        i64::try_from(value)
+            .map(|value| SpannedValue::Other(value.into()))
+            .map_err(|_| E::custom("integer out of range"))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(SpannedValue::Other(value.into()))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(SpannedValue::Other(value.into()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut array = vec![];
+        while let Some(value) = seq.next_element()? {
+            array.push(value);
+        }
+        Ok(SpannedValue::Array(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut table = vec![];
+        while let Some(entry) = map.next_entry()? {
+            table.push(entry);
+        }
+        Ok(SpannedValue::Table(SpannedTable(table)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use indoc::indoc;
+
+    fn check_diagnostics(test_name: &str, config: &str) -> Vec<Diagnostic> {
+        let dir = TempDir::new(test_name);
+        let path = dir.write("config.toml", config);
+        let mut checker = Checker::default();
+        checker.check_file(&path);
+        checker.diagnostics
+    }
+
+    fn check_str(test_name: &str, config: &str) -> Vec<String> {
+        check_diagnostics(test_name, config)
+            .iter()
+            .map(|diagnostic| {
+                let (line, column) = diagnostic.location.unwrap_or_default();
+                format!("{}:{}: {}", line, column, diagnostic.severity)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let diagnostics = check_str(
+            "valid",
+            indoc! {r#"
+                fg = "red"
+                [keybindings]
+                "a" = [ "exit" ]
+            "#},
+        );
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_all_errors_reported() {
+        let diagnostics = check_str(
+            "errors",
+            indoc! {r#"
+                fg = "reddish"
+                interval = "fast"
+                unknown = 1
+                unbind = [ "q", "hyper+q" ]
+                [keybindings]
+                "hyper+a" = [ "exit" ]
+                "b" = [ "exit", "fly away" ]
+                "c" = []
+                [[columns]]
+                max-width = 0
+            "#},
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                "1:1: error",
+                "2:1: error",
+                "3:1: error",
+                "4:17: error",
+                "6:1: error",
+                "7:17: error",
+                "9:1: error",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let diagnostics = check_str(
+            "warnings",
+            indoc! {r#"
+                [keybindings]
+                "ctrl+i" = [ "exit" ]
+                "q" = [ "reload" ]
+                "r" = [ "reload" ]
+                "g" = []
+            "#},
+        );
+        assert_eq!(diagnostics, vec!["2:1: warning", "3:1: warning"]);
+    }
+
+    #[test]
+    fn test_merged_errors() {
+        let diagnostics = check_str(
+            "merged",
+            indoc! {r#"
+                fg = "reddish"
+                fields = "NAME"
+                [profile.a]
+                header-lines = 1
+                [profile.b]
+                field-separator = ","
+            "#},
+        );
+        // Profile "a" inherits the error, which is therefore not reported
+        // again, while profile "b" causes another one.
+        assert_eq!(diagnostics, vec!["1:1: error", "2:1: error", "2:1: error"]);
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let diagnostics = check_str(
+            "values",
+            indoc! {r#"
+                interval = -1.0
+                grace-period = nan
+                command-timeout = 1.0
+                exec-each-concurrency = 0
+                working-dir = "does-not-exist"
+            "#},
+        );
+        assert_eq!(
+            diagnostics,
+            vec!["1:1: error", "2:1: error", "4:1: error", "5:1: error"]
+        );
+    }
+
+    #[test]
+    fn test_operation_errors_explained() {
+        let diagnostics = check_diagnostics(
+            "operations",
+            indoc! {r#"
+                [keybindings]
+                "a" = [ "exec --timeout 5 -- ls" ]
+            "#},
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("Missing unit"));
+    }
+
+    #[test]
+    fn test_cyclic_include() {
+        let diagnostics = check_str("cyclic", r#"include = [ "config.toml" ]"#);
+        assert_eq!(diagnostics, vec!["1:13: error"]);
+    }
+}
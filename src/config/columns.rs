@@ -0,0 +1,231 @@
+use super::style::parse_color;
+use anyhow::{bail, Result};
+use ratatui::style::{Modifier, Style};
+use serde::{Deserialize, Serialize};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The symbol appended to cells that were truncated to fit their column.
+const ELLIPSIS: char = '…';
+
+/// The display settings of all columns, i.e. of the displayed fields in the
+/// order they are displayed in. Columns without settings are left aligned,
+/// unstyled and as wide as their widest cell.
+#[derive(Default)]
+pub struct Columns(Vec<Column>);
+
+/// The display settings of a single column.
+struct Column {
+    style: Style,
+    alignment: ColumnAlignment,
+    min_width: usize,
+    max_width: Option<usize>,
+}
+
+/// The horizontal alignment of the cells within a column.
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnAlignment {
+    #[default]
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TomlColumn {
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: Option<bool>,
+
+    #[serde(rename = "align")]
+    alignment: Option<ColumnAlignment>,
+
+    #[serde(rename = "min-width")]
+    min_width: Option<usize>,
+
+    #[serde(rename = "max-width")]
+    max_width: Option<usize>,
+}
+
+impl TryFrom<Vec<TomlColumn>> for Columns {
+    type Error = anyhow::Error;
+    fn try_from(toml: Vec<TomlColumn>) -> Result<Self, Self::Error> {
+        let columns = toml
+            .into_iter()
+            .map(Column::try_from)
+            .collect::<Result<_>>()?;
+        Ok(Self(columns))
+    }
+}
+
+impl TryFrom<TomlColumn> for Column {
+    type Error = anyhow::Error;
+    fn try_from(toml: TomlColumn) -> Result<Self, Self::Error> {
+        let min_width = toml.min_width.unwrap_or(0);
+        if let Some(max_width) = toml.max_width {
+            if max_width == 0 {
+                bail!("The max-width of a column must be at least 1");
+            }
+            if min_width > max_width {
+                bail!(
+                    "The min-width ({}) of a column must not exceed its max-width ({})",
+                    min_width,
+                    max_width
+                );
+            }
+        }
+
+        // Only the configured attributes are set, so that the column style
+        // can be applied on top of the line style.
+        let mut style = Style::default();
+        if toml.fg.is_some() {
+            style = style.fg(parse_color(toml.fg)?);
+        }
+        if toml.bg.is_some() {
+            style = style.bg(parse_color(toml.bg)?);
+        }
+        match toml.bold {
+            Some(true) => style = style.add_modifier(Modifier::BOLD),
+            Some(false) => style = style.remove_modifier(Modifier::BOLD),
+            None => {}
+        }
+
+        Ok(Self {
+            style,
+            alignment: toml.alignment.unwrap_or_default(),
+            min_width,
+            max_width: toml.max_width,
+        })
+    }
+}
+
+impl Columns {
+    /// The style of the column at the given index.
+    pub fn style(&self, index: usize) -> Style {
+        self.0
+            .get(index)
+            .map(|column| column.style)
+            .unwrap_or_default()
+    }
+
+    /// Compute the width of every column, which is the width of its widest
+    /// cell, limited by the column's min and max width.
+    pub fn widths(&self, rows: &[Vec<&str>]) -> Vec<usize> {
+        let mut widths: Vec<usize> = vec![];
+        for row in rows {
+            if widths.len() < row.len() {
+                widths.resize(row.len(), 0);
+            }
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.width());
+            }
+        }
+
+        for (width, column) in widths.iter_mut().zip(&self.0) {
+            *width = (*width).max(column.min_width);
+            if let Some(max_width) = column.max_width {
+                *width = (*width).min(max_width);
+            }
+        }
+        widths
+    }
+
+    /// Truncate and align every cell of a row to the width of its column.
+    pub fn format_row(&self, row: &[&str], widths: &[usize]) -> Vec<String> {
+        row.iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(idx, (cell, &width))| {
+                let alignment = self
+                    .0
+                    .get(idx)
+                    .map(|column| column.alignment)
+                    .unwrap_or_default();
+                format_cell(cell, width, alignment)
+            })
+            .collect()
+    }
+}
+
+/// Truncate a cell that is wider than the given width (marking the
+/// truncation with an ellipsis), and pad it according to its alignment.
+fn format_cell(cell: &str, width: usize, alignment: ColumnAlignment) -> String {
+    let cell = if cell.width() > width {
+        let mut truncated = String::new();
+        let mut truncated_width = 0;
+        for c in cell.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if truncated_width + char_width >= width {
+                break;
+            }
+            truncated.push(c);
+            truncated_width += char_width;
+        }
+        truncated.push(ELLIPSIS);
+        truncated
+    } else {
+        cell.to_owned()
+    };
+
+    let padding = width.saturating_sub(cell.width());
+    let (left, right) = match alignment {
+        ColumnAlignment::Left => (0, padding),
+        ColumnAlignment::Right => (padding, 0),
+        ColumnAlignment::Center => (padding / 2, padding - padding / 2),
+    };
+    format!("{}{}{}", " ".repeat(left), cell, " ".repeat(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(toml: &str) -> Columns {
+        #[derive(Deserialize)]
+        struct Toml {
+            columns: Vec<TomlColumn>,
+        }
+        let toml: Toml = toml::from_str(toml).unwrap();
+        toml.columns.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_widths() {
+        let columns = columns(
+            r#"
+            [[columns]]
+            min-width = 4
+            [[columns]]
+            max-width = 3
+            "#,
+        );
+        let rows = vec![vec!["a", "bbbbb", "cc"], vec!["aa", "b"]];
+        assert_eq!(columns.widths(&rows), vec![4, 3, 2]);
+    }
+
+    #[test]
+    fn test_alignment() {
+        assert_eq!(format_cell("ab", 5, ColumnAlignment::Left), "ab   ");
+        assert_eq!(format_cell("ab", 5, ColumnAlignment::Right), "   ab");
+        assert_eq!(format_cell("ab", 5, ColumnAlignment::Center), " ab  ");
+    }
+
+    #[test]
+    fn test_truncation() {
+        assert_eq!(format_cell("abcdef", 4, ColumnAlignment::Left), "abc…");
+        assert_eq!(format_cell("abcdef", 1, ColumnAlignment::Right), "…");
+        assert_eq!(format_cell("日本語", 4, ColumnAlignment::Left), "日… ");
+    }
+
+    #[test]
+    fn test_invalid_widths() {
+        let invalid = |toml: &str| -> bool {
+            let column: TomlColumn = toml::from_str(toml).unwrap();
+            Column::try_from(column).is_err()
+        };
+        assert!(invalid("max-width = 0"));
+        assert!(invalid("min-width = 5\nmax-width = 4"));
+        assert!(!invalid("min-width = 4\nmax-width = 4"));
+    }
+}
@@ -1,37 +1,73 @@
 use anyhow::{bail, Error, Result};
-use ranges::{GenericRange, Ranges};
-use serde::{self, Deserialize};
-use std::{ops::RangeInclusive, str::FromStr};
+use itertools::Itertools;
+use serde::{self, Deserialize, Serialize};
+use std::{fmt, str::FromStr};
 
-/// A collection of field selection ranges. The overlapping of multiple ranges
-/// is tolerated and should be optimized by the underlying data structure.
+/// An ordered collection of field selections. Fields are displayed in the
+/// order in which they are selected. The overlapping of multiple selections
+/// is tolerated, but every field is only displayed once, at the position of
+/// its first selection.
 #[derive(Clone, Deserialize)]
-pub struct FieldSelections(#[serde(deserialize_with = "deserialize_ranges")] Ranges<usize>);
+pub struct FieldSelections(
+    #[serde(deserialize_with = "deserialize_selections")] Vec<FieldSelection>,
+);
 
 /// Describes a range of fields that should be included in the selection.
 /// Must always contain a starting field. The format is: "a|a-b|a-".
+#[derive(Clone, Debug, PartialEq)]
 struct FieldSelection {
-    start: usize,
-    end: Option<usize>,
+    start: FieldIndex,
+    end: Option<FieldIndex>,
+}
+
+/// Identifies a single field. The format is: "N|-N|NAME|\"NAME\"", where a
+/// quoted name may contain "-" and ",", or start with a digit.
+#[derive(Clone, Debug, PartialEq)]
+enum FieldIndex {
+    /// The Nth field, counting from the start (field indexes start at 1).
+    FromStart(usize),
+    /// The Nth field, counting from the end (the last field is -1).
+    FromEnd(usize),
+    /// The field with this name in the first header line.
+    Name(String),
 }
 
 impl FromStr for FieldSelections {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let ranges = s
-            .split(',')
-            .map(|s| {
-                let field: FieldSelection = s.parse()?;
-                let range: RangeInclusive<usize> = field.into();
-                Ok(GenericRange::from(range))
-            })
+        let selections = split_unquoted(s, ',')
+            .into_iter()
+            .map(str::parse)
             .collect::<Result<_>>()?;
+        Ok(FieldSelections(selections))
+    }
+}
 
-        Ok(FieldSelections(ranges))
+/// The indexes of all occurrences of the character that aren't inside double
+/// quotes.
+fn unquoted_indexes(s: &str, c: char) -> impl Iterator<Item = usize> + '_ {
+    let mut quoted = false;
+    s.char_indices().filter_map(move |(i, current)| {
+        if current == '"' {
+            quoted = !quoted;
+        }
+        (current == c && !quoted).then_some(i)
+    })
+}
+
+/// Split the string at every delimiter that isn't inside double quotes.
+fn split_unquoted(s: &str, delimiter: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    for i in unquoted_indexes(s, delimiter) {
+        parts.push(&s[start..i]);
+        start = i + delimiter.len_utf8();
     }
+    parts.push(&s[start..]);
+    parts
 }
 
-fn deserialize_ranges<'de, D>(deserializer: D) -> Result<Ranges<usize>, D::Error>
+fn deserialize_selections<'de, D>(deserializer: D) -> Result<Vec<FieldSelection>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
@@ -41,47 +77,169 @@ where
         .map(|fs| fs.0)
 }
 
+impl Serialize for FieldSelections {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for FieldSelections {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.iter().join(","))
+    }
+}
+
+impl fmt::Display for FieldSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.end {
+            Some(end) if *end == self.start => write!(f, "{}", self.start),
+            Some(end) => write!(f, "{}-{}", self.start, end),
+            None => write!(f, "{}-", self.start),
+        }
+    }
+}
+
+impl fmt::Display for FieldIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FromStart(n) => write!(f, "{}", n),
+            Self::FromEnd(n) => write!(f, "-{}", n),
+            Self::Name(name) if Self::needs_quotes(name) => write!(f, "\"{}\"", name),
+            Self::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
 impl FieldSelections {
-    /// Check if a field, indicated by its array index, should be selected.
-    /// The field selections start counting at 1, while indexes start at 0.
-    pub fn contains(&self, index: usize) -> bool {
-        self.0.contains(&(index + 1))
+    /// Resolve the selections into the ordered array indexes of all fields
+    /// that should be selected in a line containing `field_count` fields.
+    /// Field names are resolved against the fields of the first header line.
+    pub fn resolve(&self, field_count: usize, header: &[&str]) -> Vec<usize> {
+        let mut already_selected = vec![false; field_count];
+        let mut indexes = vec![];
+        for selection in &self.0 {
+            let Some(start) = selection
+                .start
+                .resolve(field_count, header)
+                .filter(|&start| start < field_count)
+            else {
+                continue;
+            };
+            let end = match &selection.end {
+                Some(end) => match end.resolve(field_count, header) {
+                    Some(end) => end.min(field_count - 1),
+                    None => continue,
+                },
+                None => field_count - 1,
+            };
+            for (index, already_selected) in already_selected
+                .iter_mut()
+                .enumerate()
+                .take(end + 1)
+                .skip(start)
+            {
+                if !*already_selected {
+                    *already_selected = true;
+                    indexes.push(index);
+                }
+            }
+        }
+        indexes
+    }
+
+    /// Check if any field is selected by its name.
+    pub fn contains_names(&self) -> bool {
+        self.0.iter().any(|selection| {
+            matches!(selection.start, FieldIndex::Name(_))
+                || matches!(selection.end, Some(FieldIndex::Name(_)))
+        })
+    }
+}
+
+impl FieldIndex {
+    /// Whether the name can only be parsed when quoted.
+    fn needs_quotes(name: &str) -> bool {
+        name.contains(['-', ',']) || name.starts_with(|c: char| c.is_ascii_digit())
+    }
+
+    /// Resolve into an array index, which might be out of bounds for fields
+    /// counted from the start. Returns None if the field doesn't exist.
+    fn resolve(&self, field_count: usize, header: &[&str]) -> Option<usize> {
+        match self {
+            Self::FromStart(n) => Some(n - 1),
+            Self::FromEnd(n) => field_count.checked_sub(*n),
+            Self::Name(name) => header.iter().position(|field| field == name),
+        }
     }
 }
 
 impl FromStr for FieldSelection {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (start, end) = match *s.splitn(2, '-').collect::<Vec<_>>().as_slice() {
-            [x] => {
-                let x_parsed = x.parse::<usize>()?;
-                (x_parsed, Some(x_parsed))
-            }
-            [x, ""] => (x.parse::<usize>()?, None),
-            [x, y] => (x.parse::<usize>()?, Some(y.parse::<usize>()?)),
-            _ => bail!(
-                "Failed to parse \"{}\" as field selection, expected format is a|a-b|a-",
-                s
-            ),
-        };
+        // A single field takes precedence over a range, because the "-" of a
+        // negative index could otherwise be mistaken for a range separator.
+        if let Ok(index) = s.parse::<FieldIndex>() {
+            return Ok(FieldSelection {
+                start: index.clone(),
+                end: Some(index),
+            });
+        }
 
-        if matches!(start, 0) || matches!(end, Some(0)) {
+        // The range separator is the first "-" that doesn't negate the start,
+        // and isn't part of a quoted name.
+        let Some(separator_idx) = unquoted_indexes(s, '-').find(|&i| i > 0) else {
             bail!(
-                "Failed to parse \"{}\" as field selection, 0 is not a valid field",
+                "Failed to parse \"{}\" as field selection, expected format is a|a-b|a-",
                 s
             );
-        }
+        };
+        let (start, end) = (&s[..separator_idx], &s[separator_idx + 1..]);
+
+        let parse_index = |index: &str| {
+            index.parse::<FieldIndex>().map_err(|e| {
+                e.context(format!(
+                    "Failed to parse \"{}\" as field selection, expected format is a|a-b|a-",
+                    s
+                ))
+            })
+        };
+        let start = parse_index(start)?;
+        let end = match end {
+            "" => None,
+            end => Some(parse_index(end)?),
+        };
 
         Ok(FieldSelection { start, end })
     }
 }
 
-impl From<FieldSelection> for RangeInclusive<usize> {
-    fn from(value: FieldSelection) -> Self {
-        match (value.start, value.end) {
-            (start, Some(end)) => RangeInclusive::new(start, end),
-            (start, None) => RangeInclusive::new(start, usize::MAX),
+impl FromStr for FieldIndex {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let quoted_name = s
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .filter(|name| !name.is_empty() && !name.contains('"'));
+        let index = if let Some(name) = quoted_name {
+            Self::Name(name.to_owned())
+        } else if let Some(n) = s.strip_prefix('-') {
+            Self::FromEnd(n.parse()?)
+        } else if s.starts_with(|c: char| c.is_ascii_digit()) {
+            Self::FromStart(s.parse()?)
+        } else if s.is_empty() || s.contains(['-', '"']) {
+            bail!(
+                "Failed to parse \"{}\" as field, expected format is N|-N|NAME|\"NAME\"",
+                s
+            );
+        } else {
+            Self::Name(s.to_owned())
+        };
+
+        if matches!(index, Self::FromStart(0) | Self::FromEnd(0)) {
+            bail!("Failed to parse \"{}\" as field, 0 is not a valid field", s);
         }
+
+        Ok(index)
     }
 }
 
@@ -89,12 +247,16 @@ impl From<FieldSelection> for RangeInclusive<usize> {
 mod tests {
     use super::*;
 
+    fn resolve(selections: &str, field_count: usize, header: &[&str]) -> Vec<usize> {
+        selections
+            .parse::<FieldSelections>()
+            .unwrap()
+            .resolve(field_count, header)
+    }
+
     #[test]
     fn test_inclusive_range() {
-        assert_eq!(
-            "1,2-5,8-".parse::<FieldSelections>().unwrap().0,
-            Ranges::from(vec![1..=1, 2..=5, 8..=usize::MAX])
-        );
+        assert_eq!(resolve("1,2-5,8-", 10, &[]), vec![0, 1, 2, 3, 4, 7, 8, 9]);
     }
 
     #[test]
@@ -104,16 +266,87 @@ mod tests {
                 .parse::<FieldSelections>()
                 .unwrap()
                 .0,
-            Ranges::from(vec![1..=1, usize::MAX..=usize::MAX])
+            vec![
+                FieldSelection {
+                    start: FieldIndex::FromStart(1),
+                    end: Some(FieldIndex::FromStart(1)),
+                },
+                FieldSelection {
+                    start: FieldIndex::FromStart(usize::MAX),
+                    end: Some(FieldIndex::FromStart(usize::MAX)),
+                },
+            ]
         );
+        assert_eq!(resolve(&format!("1,{}", usize::MAX), 3, &[]), vec![0]);
     }
 
     #[test]
     fn test_overlapping_ranges() {
+        assert_eq!(resolve("1-3,1-4,2-5", 10, &[]), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_reordering() {
+        assert_eq!(resolve("3,1,2", 3, &[]), vec![2, 0, 1]);
+        assert_eq!(resolve("3-,1", 4, &[]), vec![2, 3, 0]);
+    }
+
+    #[test]
+    fn test_negative_indexes() {
+        assert_eq!(resolve("-1", 4, &[]), vec![3]);
+        assert_eq!(resolve("-2-", 4, &[]), vec![2, 3]);
+        assert_eq!(resolve("2--2", 4, &[]), vec![1, 2]);
+        assert_eq!(resolve("-3--2,-1", 4, &[]), vec![1, 2, 3]);
+        assert_eq!(resolve("-5", 4, &[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_header_names() {
+        let header = ["NAME", "READY", "STATUS", "AGE"];
+        assert_eq!(resolve("NAME,STATUS,3", 4, &header), vec![0, 2]);
+        assert_eq!(resolve("STATUS,NAME", 4, &header), vec![2, 0]);
+        assert_eq!(resolve("READY-STATUS,-1", 4, &header), vec![1, 2, 3]);
+        assert_eq!(resolve("STATUS-", 4, &header), vec![2, 3]);
+        assert_eq!(resolve("MISSING,AGE", 4, &header), vec![3]);
+    }
+
+    #[test]
+    fn test_quoted_header_names() {
+        let header = ["NAME", "READY-STATUS", "A,B", "1H"];
         assert_eq!(
-            "1-3,1-4,2-5".parse::<FieldSelections>().unwrap().0,
-            Ranges::from(vec![1..=5])
+            resolve(r#""READY-STATUS","1H","A,B""#, 4, &header),
+            vec![1, 3, 2]
         );
+        assert_eq!(resolve(r#""NAME"-"READY-STATUS""#, 4, &header), vec![0, 1]);
+        assert_eq!(resolve(r#""A,B"-,1"#, 4, &header), vec![2, 3, 0]);
+        assert_eq!(resolve(r#"-1-"1H""#, 4, &header), vec![3]);
+    }
+
+    #[test]
+    fn test_contains_names() {
+        assert!(!"1,-1,2-"
+            .parse::<FieldSelections>()
+            .unwrap()
+            .contains_names());
+        assert!("1-NAME"
+            .parse::<FieldSelections>()
+            .unwrap()
+            .contains_names());
+    }
+
+    #[test]
+    fn test_display() {
+        for selections in [
+            "1,2-5,8-",
+            "-1,-3--2",
+            "NAME,READY-STATUS,3-",
+            r#""READY-STATUS","A,B"-"1H""#,
+        ] {
+            assert_eq!(
+                selections.parse::<FieldSelections>().unwrap().to_string(),
+                selections
+            );
+        }
     }
 
     #[test]
@@ -121,4 +354,17 @@ mod tests {
     fn test_invalid() {
         let _: FieldSelections = "0".parse().unwrap();
     }
+
+    #[test]
+    fn test_invalid_formats() {
+        assert!("-0".parse::<FieldSelections>().is_err());
+        assert!("1-0".parse::<FieldSelections>().is_err());
+        assert!("".parse::<FieldSelections>().is_err());
+        assert!("1,".parse::<FieldSelections>().is_err());
+        assert!("1a".parse::<FieldSelections>().is_err());
+        assert!(r#""""#.parse::<FieldSelections>().is_err());
+        assert!(r#"""#.parse::<FieldSelections>().is_err());
+        assert!(r#"A"B"#.parse::<FieldSelections>().is_err());
+        assert!(r#""A"B""#.parse::<FieldSelections>().is_err());
+    }
 }
@@ -2,9 +2,7 @@ mod field_selection;
 mod field_separator;
 
 use anyhow::{bail, Result};
-use itertools::Itertools;
-use std::io::Write;
-use tabwriter::TabWriter;
+use std::str::Split;
 
 pub use self::field_selection::FieldSelections;
 pub use self::field_separator::FieldSeparator;
@@ -21,55 +19,129 @@ impl Fields {
     pub fn try_new(
         separator: Option<FieldSeparator>,
         selections: Option<FieldSelections>,
+        header_lines: usize,
     ) -> Result<Self> {
         if selections.is_some() && separator.is_none() {
             bail!("Cannot specify/apply field selections without specifying a field separator");
         }
+        if header_lines == 0
+            && selections
+                .as_ref()
+                .is_some_and(FieldSelections::contains_names)
+        {
+            bail!("Cannot select fields by name without specifying at least one header line");
+        }
         Ok(Self {
             separator,
             selections,
         })
     }
+
+    /// Split a line into its fields, if a field separator is present. This is
+    /// the exact splitting that is used when formatting lines as a table.
+    pub fn split<'a, 'b>(&'b self, line: &'a str) -> Option<Split<'a, &'b String>> {
+        self.separator
+            .as_ref()
+            .map(|separator| line.split(separator.as_ref()))
+    }
 }
 
-/// Format a string as a table that has its fields separated by an elastic
-/// tabstop, and only displays the fields that should be selected.
-/// Only applies any formatting if a separator or selection is present.
-pub trait TableFormatter {
-    fn format_as_table(&self, fields: &Fields) -> Result<Option<String>>;
+#[cfg(test)]
+impl Fields {
+    /// Fields that are split by the separator, without any selections.
+    pub fn separated_by(separator: &str) -> Self {
+        Self {
+            separator: separator.parse().ok(),
+            selections: None,
+        }
+    }
 }
 
-impl TableFormatter for &str {
-    fn format_as_table(&self, fields: &Fields) -> Result<Option<String>> {
-        let table = match &fields.separator {
-            Some(separator) => {
-                let separator = separator.as_ref();
+/// Format a string as a table, whose rows are the lines of the string and
+/// whose cells are the fields that should be selected, in selection order.
+/// Only applies any formatting if a separator is present.
+pub trait TableFormatter<'a> {
+    fn format_as_table(&self, fields: &Fields) -> Option<Vec<Vec<&'a str>>>;
+}
 
-                let formatted_lines = match &fields.selections {
-                    Some(selections) => self
-                        .lines()
-                        .map(|line| {
-                            line.split(separator)
-                                .enumerate()
-                                // TODO: seems inefficient, try applying selection to whole line at a time
-                                .filter_map(|(idx, field)| {
-                                    selections.contains(idx).then_some(field)
-                                })
-                                .join("\t")
-                        })
-                        .join("\n"),
-                    None => self.replace(separator, "\t"),
-                };
+impl<'a> TableFormatter<'a> for &'a str {
+    fn format_as_table(&self, fields: &Fields) -> Option<Vec<Vec<&'a str>>> {
+        fields.separator.as_ref()?;
+        let lines: &'a str = self;
 
-                let mut tw = TabWriter::new(vec![]);
-                write!(tw, "{}", formatted_lines)?;
-                tw.flush()?;
+        let table = match &fields.selections {
+            Some(selections) => {
+                // Field names are resolved against the first line, which must
+                // be a header line if names are selected.
+                let header: Vec<&str> = lines
+                    .lines()
+                    .next()
+                    .and_then(|line| fields.split(line))
+                    .map(Iterator::collect)
+                    .unwrap_or_default();
 
-                let table = String::from_utf8(tw.into_inner()?)?;
-                Some(table)
+                lines
+                    .lines()
+                    .filter_map(|line| fields.split(line))
+                    .map(|line_fields| {
+                        let line_fields: Vec<&str> = line_fields.collect();
+                        selections
+                            .resolve(line_fields.len(), &header)
+                            .into_iter()
+                            .map(|idx| line_fields[idx])
+                            .collect()
+                    })
+                    .collect()
             }
-            None => None,
+            None => lines
+                .lines()
+                .filter_map(|line| fields.split(line))
+                .map(Iterator::collect)
+                .collect(),
         };
-        Ok(table)
+        Some(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_without_separator() -> Result<()> {
+        let fields = Fields::try_new(None, None, 0)?;
+        assert!(fields.split("a,b,c").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_keeps_empty_fields() -> Result<()> {
+        let fields = Fields::try_new(Some(",".parse()?), None, 0)?;
+        assert_eq!(
+            fields.split("a,b,,d").map(Iterator::collect::<Vec<_>>),
+            Some(vec!["a", "b", "", "d"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_as_table() -> Result<()> {
+        let fields = Fields::try_new(Some(",".parse()?), Some("NAME,-1".parse()?), 1)?;
+        assert_eq!(
+            "NAME,AGE,STATUS\na,1,ok\nb,2".format_as_table(&fields),
+            Some(vec![
+                vec!["NAME", "STATUS"],
+                vec!["a", "ok"],
+                vec!["b", "2"]
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_names_require_header_lines() -> Result<()> {
+        assert!(Fields::try_new(Some(",".parse()?), Some("NAME".parse()?), 0).is_err());
+        assert!(Fields::try_new(Some(",".parse()?), Some("NAME".parse()?), 1).is_ok());
+        Ok(())
     }
 }
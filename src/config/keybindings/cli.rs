@@ -0,0 +1,222 @@
+use super::ClapKeybindings;
+use anyhow::{bail, Result};
+use std::{iter::Peekable, str::Chars};
+
+/// The characters that have to be escaped with a backslash in unquoted keys
+/// and operations.
+const SPECIAL_CHARS: [char; 4] = [',', ':', '+', '\\'];
+
+/// Parse a comma-separated list of keybindings in the format `KEY:OP[+OP]*`.
+/// Any special character (`,`, `:`, `+` or `\`) in a key or operation must
+/// either be escaped with a backslash, or the whole key or operation must be
+/// quoted. Inside double quotes, only `"` and `\` have to be escaped, while
+/// single quotes don't support escaping. A key without any operations (e.g.
+/// `q:`) is unbound.
+pub fn parse_str(s: &str) -> Result<ClapKeybindings> {
+    let invalid_format = || format!("expected \"KEY:OP[+OP]*[,KEY:OP[+OP]*]*\", found \"{}\"", s);
+
+    let mut chars = s.chars().peekable();
+    let mut keybindings = vec![];
+    loop {
+        let (key, terminator) = parse_item(&mut chars, &[':', ','])?;
+        if terminator != Some(':') || key.is_empty() {
+            bail!("invalid format: {}", invalid_format());
+        }
+
+        let mut operations = vec![];
+        let terminator = loop {
+            let (operation, terminator) = parse_item(&mut chars, &['+', ','])?;
+            if operation.is_empty() {
+                // Only a key without any operations may be empty.
+                if operations.is_empty() && terminator != Some('+') {
+                    break terminator;
+                }
+                bail!("empty operation: {}", invalid_format());
+            }
+            operations.push(operation);
+            if terminator != Some('+') {
+                break terminator;
+            }
+        };
+        keybindings.push((key, operations));
+
+        if terminator.is_none() {
+            return Ok(keybindings);
+        }
+    }
+}
+
+/// Parse a (possibly quoted) key or operation, until reaching one of the
+/// terminators or the end. Returns the unescaped item along with the
+/// terminator that was reached.
+fn parse_item(chars: &mut Peekable<Chars>, terminators: &[char]) -> Result<(String, Option<char>)> {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+    let item = match chars.peek() {
+        Some(&quote @ ('"' | '\'')) => {
+            chars.next();
+            let item = parse_quoted(chars, quote)?;
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.peek() {
+                Some(c) if !terminators.contains(c) => {
+                    bail!("unexpected character '{}' after closing quote", c)
+                }
+                _ => item,
+            }
+        }
+        _ => {
+            let mut item = String::new();
+            while let Some(c) = chars.next_if(|c| !terminators.contains(c)) {
+                match c {
+                    '\\' => match chars.next_if(|c| SPECIAL_CHARS.contains(c)) {
+                        Some(escaped) => item.push(escaped),
+                        None => item.push(c),
+                    },
+                    c => item.push(c),
+                }
+            }
+            item.trim_end().to_owned()
+        }
+    };
+
+    Ok((item, chars.next()))
+}
+
+/// Parse the rest of a quoted string, whose opening quote was already
+/// consumed.
+fn parse_quoted(chars: &mut Peekable<Chars>, quote: char) -> Result<String> {
+    let mut item = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c == quote => return Ok(item),
+            '\\' if quote == '"' => match chars.next_if(|&c| c == '"' || c == '\\') {
+                Some(escaped) => item.push(escaped),
+                None => item.push(c),
+            },
+            c => item.push(c),
+        }
+    }
+    bail!("missing closing quote {}", quote)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OperationsParsed;
+
+    /// Escape all special characters, so that the string is parsed as is.
+    fn escape(s: &str) -> String {
+        s.chars()
+            .flat_map(|c| {
+                let backslash = SPECIAL_CHARS.contains(&c).then_some('\\');
+                backslash.into_iter().chain([c])
+            })
+            .collect()
+    }
+
+    /// Quote a string, so that it is parsed as is.
+    fn quote(s: &str) -> String {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+
+    fn keybinding(key: &str, operations: &[&str]) -> (String, Vec<String>) {
+        (
+            key.to_owned(),
+            operations.iter().map(|op| op.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        assert_eq!(
+            parse_str("q:select+exit,r:reload")?,
+            vec![
+                keybinding("q", &["select", "exit"]),
+                keybinding("r", &["reload"])
+            ]
+        );
+        assert_eq!(
+            parse_str(" j : cursor down 1 + select ")?,
+            vec![keybinding("j", &["cursor down 1", "select"])]
+        );
+        assert_eq!(parse_str("q:")?, vec![keybinding("q", &[])]);
+        assert_eq!(
+            parse_str("q:,r:reload")?,
+            vec![keybinding("q", &[]), keybinding("r", &["reload"])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_escaped() -> Result<()> {
+        assert_eq!(
+            parse_str(r"o:exec -- open https\://a.com/?q=a\+b\,c")?,
+            vec![keybinding("o", &["exec -- open https://a.com/?q=a+b,c"])]
+        );
+        assert_eq!(
+            parse_str(r"\,:exit,\::exit,ctrl++:exit")?,
+            vec![
+                keybinding(",", &["exit"]),
+                keybinding(":", &["exit"]),
+                keybinding("ctrl++", &["exit"])
+            ]
+        );
+        // Backslashes before other characters are kept.
+        assert_eq!(
+            parse_str(r"p:exec -- printf 'a\n' \\+ exit")?,
+            vec![keybinding("p", &[r"exec -- printf 'a\n' \", "exit"])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_quoted() -> Result<()> {
+        assert_eq!(
+            parse_str(r#"e:"exec -- echo a+b, \"c\"" + exit,':':'exec -- echo "\"'"#)?,
+            vec![
+                keybinding("e", &[r#"exec -- echo a+b, "c""#, "exit"]),
+                keybinding(":", &[r#"exec -- echo "\""#])
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_str("").is_err());
+        assert!(parse_str("q").is_err());
+        assert!(parse_str(":exit").is_err());
+        assert!(parse_str("q:exit+").is_err());
+        assert!(parse_str("q:+exit").is_err());
+        assert!(parse_str("q:exit,").is_err());
+        assert!(parse_str(r#"q:"exit"#).is_err());
+        assert!(parse_str(r#"q:"exit" reload"#).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let operations: OperationsParsed = vec![
+            "exec -- echo a+b,c:d".to_owned(),
+            r#"exec & -- printf "%s\n" "$line" \ 'x'"#.to_owned(),
+            "set-env x -- echo 'a, b' + \"c\"".to_owned(),
+            "cursor down 1".to_owned(),
+        ]
+        .try_into()?;
+
+        for format in [escape, quote] {
+            let formatted = (&operations)
+                .into_iter()
+                .map(|op| format(&op.to_string()))
+                .collect::<Vec<_>>()
+                .join("+");
+            let parsed = parse_str(&format!("{}:{}", format("ctrl+,"), formatted))?;
+            assert_eq!(parsed.len(), 1);
+            let (key, parsed_operations) = parsed.into_iter().next().expect("one keybinding");
+            assert_eq!(key, "ctrl+,");
+
+            let parsed_operations: OperationsParsed = parsed_operations.try_into()?;
+            assert_eq!(parsed_operations.to_string(), operations.to_string());
+        }
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use anyhow::{bail, Context, Error, Result};
 use crossterm::event::{KeyCode as CKeyCode, KeyEvent as CKeyEvent, KeyModifiers as CKeyModifiers};
 use derive_more::From;
 use parse_display::{Display, FromStr};
-use std::{fmt, str};
+use std::{fmt, iter, str};
 
 /// The specific combinations of modifiers and key codes that we allow/handle.
 #[derive(Hash, Eq, PartialEq, PartialOrd, Ord, From, Clone, Debug)]
@@ -51,6 +51,51 @@ enum KeyCode {
     F(u8),
 }
 
+impl KeyModifier {
+    /// The modifier documented after `previous`, or the first one if
+    /// `previous` is `None`. Exhaustively matched, so that new modifiers
+    /// can't be added without deciding whether they are documented.
+    fn next_documented(previous: Option<&Self>) -> Option<Self> {
+        match previous {
+            None => Some(Self::Alt),
+            Some(Self::Alt) => Some(Self::Ctrl),
+            // Shift and None can't be parsed, so they aren't documented.
+            Some(Self::Ctrl | Self::Shift | Self::None) => None,
+        }
+    }
+}
+
+impl KeyCode {
+    /// The key code documented after `previous`, or the first one if
+    /// `previous` is `None`. Exhaustively matched, so that new key codes
+    /// can't be added without documenting them. Single characters are
+    /// documented separately.
+    fn next_documented(previous: Option<&Self>) -> Option<Self> {
+        let next = match previous {
+            None => Self::Esc,
+            Some(Self::Esc) => Self::Enter,
+            Some(Self::Enter) => Self::Left,
+            Some(Self::Left) => Self::Right,
+            Some(Self::Right) => Self::Up,
+            Some(Self::Up) => Self::Down,
+            Some(Self::Down) => Self::Home,
+            Some(Self::Home) => Self::End,
+            Some(Self::End) => Self::PageUp,
+            Some(Self::PageUp) => Self::PageDown,
+            Some(Self::PageDown) => Self::BackTab,
+            Some(Self::BackTab) => Self::Backspace,
+            Some(Self::Backspace) => Self::Delete,
+            Some(Self::Delete) => Self::Insert,
+            Some(Self::Insert) => Self::Tab,
+            Some(Self::Tab) => Self::Space,
+            Some(Self::Space) => Self::F(1),
+            Some(Self::F(n)) if *n < 12 => Self::F(n + 1),
+            Some(Self::F(_) | Self::Char(_)) => return None,
+        };
+        Some(next)
+    }
+}
+
 impl str::FromStr for KeyEvent {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -72,6 +117,53 @@ impl str::FromStr for KeyEvent {
     }
 }
 
+impl KeyEvent {
+    /// The names of all modifiers, used to generate the documentation.
+    pub fn documented_modifiers() -> Vec<String> {
+        iter::successors(KeyModifier::next_documented(None), |modifier| {
+            KeyModifier::next_documented(Some(modifier))
+        })
+        .map(|modifier| modifier.to_string())
+        .collect()
+    }
+
+    /// The names of all key codes except for single characters, used to
+    /// generate the documentation.
+    pub fn documented_codes() -> Vec<String> {
+        iter::successors(KeyCode::next_documented(None), |code| {
+            KeyCode::next_documented(Some(code))
+        })
+        .map(|code| code.to_string())
+        .collect()
+    }
+
+    /// The character that this key event types when entering text, if any.
+    pub fn typed_char(&self) -> Option<char> {
+        match (&self.modifier, &self.code) {
+            (KeyModifier::None | KeyModifier::Shift, KeyCode::Char(c)) => Some(*c),
+            (KeyModifier::None | KeyModifier::Shift, KeyCode::Space) => Some(' '),
+            _ => None,
+        }
+    }
+
+    /// Explain why the terminal can never send this key event, if so.
+    pub fn unreachable_reason(&self) -> Option<&'static str> {
+        match (&self.modifier, &self.code) {
+            (KeyModifier::Alt | KeyModifier::Ctrl, KeyCode::Char(c)) if c.is_uppercase() => {
+                Some("uppercase letters are sent with shift, which can't be combined with other modifiers")
+            }
+            (KeyModifier::Ctrl, KeyCode::Char('i')) => Some("terminals send it as \"tab\""),
+            (KeyModifier::Ctrl, KeyCode::Char('m')) => Some("terminals send it as \"enter\""),
+            (KeyModifier::Ctrl, KeyCode::Char('[')) => Some("terminals send it as \"esc\""),
+            (_, KeyCode::Char(' ')) => Some("the space key is called \"space\""),
+            (KeyModifier::None, KeyCode::BackTab) => {
+                Some("terminals send it with shift, which can't be bound")
+            }
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for KeyEvent {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.modifier == KeyModifier::None {
@@ -215,6 +307,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_documented_keys_parse() -> Result<()> {
+        for modifier in KeyEvent::documented_modifiers() {
+            modifier.parse::<KeyModifier>()?;
+        }
+        for code in KeyEvent::documented_codes() {
+            code.parse::<KeyCode>()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreachable_keys() -> Result<()> {
+        for key in ["ctrl+A", "alt+P", "ctrl+i", "ctrl+m", "backtab"] {
+            assert!(key.parse::<KeyEvent>()?.unreachable_reason().is_some());
+        }
+        for key in ["A", "ctrl+a", "alt+p", "tab", "space"] {
+            assert!(key.parse::<KeyEvent>()?.unreachable_reason().is_none());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_char() -> Result<()> {
+        assert_eq!("a".parse::<KeyEvent>()?.typed_char(), Some('a'));
+        assert_eq!("A".parse::<KeyEvent>()?.typed_char(), Some('A'));
+        assert_eq!("space".parse::<KeyEvent>()?.typed_char(), Some(' '));
+        assert_eq!("ctrl+a".parse::<KeyEvent>()?.typed_char(), None);
+        assert_eq!("enter".parse::<KeyEvent>()?.typed_char(), None);
+        Ok(())
+    }
+
     #[test]
     #[should_panic]
     fn test_invalid_modifiers() {
@@ -1,19 +1,22 @@
+mod cli;
 mod key;
 mod operations;
 
+pub use cli::parse_str;
 pub use key::KeyEvent;
-pub use operations::{Operation, OperationParsed, Operations, OperationsParsed};
+pub use operations::{OperationParsed, Operations, OperationsParsed};
 
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
+use derive_more::AsRef;
 use itertools::Itertools;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
 use std::io::Write;
 use std::sync::Arc;
 use std::{collections::HashMap, fmt};
 use tabwriter::TabWriter;
 use tokio::sync::Mutex;
 
-use crate::ui::EnvVariables;
+use crate::{command::CommandSettings, ui::EnvVariables};
 
 pub struct Keybindings(HashMap<KeyEvent, Operations>);
 
@@ -25,12 +28,13 @@ impl Keybindings {
     pub fn from_parsed(
         keybindings_parsed: KeybindingsParsed,
         env_variables: &Arc<Mutex<EnvVariables>>,
+        settings: &CommandSettings,
     ) -> Self {
         Self(
             keybindings_parsed
                 .0
                 .into_iter()
-                .map(|(key, ops)| (key, Operations::from_parsed(ops, env_variables)))
+                .map(|(key, ops)| (key, Operations::from_parsed(ops, env_variables, settings)))
                 .collect(),
         )
     }
@@ -70,6 +74,8 @@ impl TryFrom<StringKeybindings> for KeybindingsParsed {
         let keybindings = value
             .0
             .into_iter()
+            // Keys without operations were unbound.
+            .filter(|(_, ops)| !ops.is_empty())
             .map(|(key, ops)| {
                 Ok((
                     key.parse()
@@ -85,9 +91,16 @@ impl TryFrom<StringKeybindings> for KeybindingsParsed {
 // TODO: remove once clap supports parsing directly into HashMap
 pub type ClapKeybindings = Vec<(String, Vec<String>)>;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, AsRef, Default)]
 pub struct StringKeybindings(HashMap<String, Vec<String>>);
 
+impl Serialize for StringKeybindings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Sorted by key, to make the output deterministic.
+        serializer.collect_map(self.0.iter().sorted())
+    }
+}
+
 impl StringKeybindings {
     pub fn merge(new_opt: Option<Self>, old_opt: Option<Self>) -> Option<Self> {
         match new_opt {
@@ -110,20 +123,3 @@ impl From<ClapKeybindings> for StringKeybindings {
         Self(clap.into_iter().collect())
     }
 }
-
-// TODO: implement FromStr trait
-// TODO: replace with nom
-// TODO: parse to Vec<Keybinding> and provide from_str for keybinding
-pub fn parse_str(s: &str) -> Result<(String, Vec<String>)> {
-    let Some((key, operations)) = s.split_once(':') else {
-        bail!("invalid format: expected \"KEY:OP[+OP]*\", found \"{}\"", s);
-    };
-
-    Ok((
-        key.to_string(),
-        operations
-            .split('+')
-            .map(|op| op.trim().to_owned())
-            .collect(),
-    ))
-}
@@ -2,12 +2,12 @@ mod operation;
 
 use std::sync::Arc;
 
-use crate::ui::EnvVariables;
+use crate::{command::CommandSettings, ui::EnvVariables};
 
 pub use self::operation::Operation;
 pub use operation::OperationParsed;
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use derive_more::{From, IntoIterator};
 use itertools::Itertools;
 use tokio::sync::Mutex;
@@ -19,16 +19,23 @@ impl Operations {
     pub fn from_parsed(
         operations_parsed: OperationsParsed,
         env_variables: &Arc<Mutex<EnvVariables>>,
+        settings: &CommandSettings,
     ) -> Self {
         Self(
             operations_parsed
                 .0
                 .into_iter()
-                .map(|op| Operation::from_parsed(op, env_variables))
+                .map(|op| Operation::from_parsed(op, env_variables, settings))
                 .collect(),
         )
     }
 
+    /// Whether the key of these operations can be queued while blocking, to
+    /// be handled later.
+    pub fn are_queueable(&self) -> bool {
+        !self.0.iter().any(|op| matches!(op, Operation::NoQueue))
+    }
+
     // TODO: find crate that removes this boilerplate
     pub fn len(&self) -> usize {
         self.0.len()
@@ -43,12 +50,7 @@ impl TryFrom<Vec<String>> for OperationsParsed {
     fn try_from(vec: Vec<String>) -> Result<Self, Self::Error> {
         let operations = vec
             .into_iter()
-            .map(|op| {
-                // TODO: error messages from the parsing of attributes inside an operation (e.g. an EnvVariable) is not displayed here
-                op.parse()
-                    .with_context(|| format!("Failed to parse operation: {}", op))
-                    .map_err(anyhow::Error::from)
-            })
+            .map(|op| OperationParsed::parse_explained(&op))
             .collect::<Result<_>>()?;
         Ok(Self(operations))
     }
@@ -62,3 +64,25 @@ impl std::fmt::Display for OperationsParsed {
         write!(f, "[ {} ]", formatted_operations)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_are_queueable() -> Result<()> {
+        let settings = test_utils::config(&["ls"]).command_settings();
+        let env_variables = Arc::new(Mutex::new(EnvVariables::default()));
+        let are_queueable = |ops: &[&str]| -> Result<bool> {
+            let ops: Vec<String> = ops.iter().map(|&op| op.to_owned()).collect();
+            let ops = Operations::from_parsed(ops.try_into()?, &env_variables, &settings);
+            Ok(ops.are_queueable())
+        };
+        assert!(are_queueable(&["cursor down 1"])?);
+        assert!(are_queueable(&["exec -- rm $line", "reload"])?);
+        assert!(!are_queueable(&["no-queue"])?);
+        assert!(!are_queueable(&["exec -- rm $line", "no-queue"])?);
+        Ok(())
+    }
+}
@@ -1,9 +1,13 @@
-use crate::command::{Blocking, CommandBuilder, NonBlocking, WithEnv, WithOutput};
-use crate::ui::{EnvVariable, EnvVariables, Event, RequestedAction, State};
-use anyhow::Result;
+use crate::command::{
+    cancel_channel, BackgroundJobs, Blocking, CommandBuilder, CommandSettings, NonBlocking,
+    WithEnv, WithOutput,
+};
+use crate::ui::{EnvVariable, EnvVariables, ErrorSource, Event, RequestedAction, State};
+use anyhow::{bail, Context, Result};
 use parse_display::{Display, FromStr};
-use std::str;
 use std::sync::Arc;
+use std::{fmt, time::Duration};
+use std::{iter, str};
 use tokio::sync::mpsc::Sender;
 use tokio::sync::Mutex;
 
@@ -27,20 +31,57 @@ pub enum OperationParsed {
     #[display("{0}")]
     SelectLine(SelectOperation),
 
+    #[display("scroll-left {0}")]
+    ScrollLeft(usize),
+
+    #[display("scroll-right {0}")]
+    ScrollRight(usize),
+
+    WrapLinesToggle,
+
     #[display("exec -- {0}")]
     ExecuteBlocking(String),
 
+    #[display("exec --timeout {0} -- {1}")]
+    ExecuteBlockingWithTimeout(Timeout, String),
+
+    #[display("exec --stdin -- {0}")]
+    ExecuteBlockingWithStdin(String),
+
+    #[display("exec --stdin --null -- {0}")]
+    ExecuteBlockingWithNulSeparatedStdin(String),
+
     #[display("exec & -- {0}")]
     ExecuteNonBlocking(String),
 
+    #[display("exec-each -- {0}")]
+    ExecuteEach(String),
+
+    #[display("exec-each & -- {0}")]
+    ExecuteEachNonBlocking(String),
+
+    #[display("exec & detach -- {0}")]
+    ExecuteDetached(String),
+
     #[display("set-env {0} -- {1}")]
     SetEnv(EnvVariable, String),
 
+    #[display("choose {0} -- {1}")]
+    Choose(EnvVariable, String),
+
     #[display("unset-env {0}")]
     UnsetEnv(EnvVariable),
 
     #[display("read-into-env {0}")]
     ReadIntoEnv(EnvVariable),
+
+    NoQueue,
+
+    #[display("cd {0}")]
+    ChangeDir(String),
+
+    #[display("confirm \"{0}\"")]
+    Confirm(String),
 }
 
 pub enum Operation {
@@ -51,15 +92,34 @@ pub enum Operation {
     HelpToggle,
     MoveCursor(MoveCursor),
     SelectLine(SelectOperation),
+    ScrollLeft(usize),
+    ScrollRight(usize),
+    WrapLinesToggle,
     ExecuteBlocking(Arc<CommandBuilder<Blocking, WithEnv>>),
+    /// Contains the separator that terminates each of the selected lines
+    /// written to stdin.
+    ExecuteBlockingWithStdin(Arc<CommandBuilder<Blocking, WithEnv>>, char),
     ExecuteNonBlocking(Arc<CommandBuilder<NonBlocking, WithEnv>>),
+    ExecuteEach(Arc<CommandBuilder<Blocking, WithEnv>>),
+    /// The executions are blocking within a background task, which reports
+    /// their summary once all of them completed.
+    ExecuteEachNonBlocking(Arc<CommandBuilder<Blocking, WithEnv>>),
+    ExecuteDetached(Arc<CommandBuilder<NonBlocking, WithEnv>>),
     SetEnv(
         EnvVariable,
         Arc<CommandBuilder<Blocking, WithEnv, WithOutput>>,
     ),
+    Choose(
+        EnvVariable,
+        Arc<CommandBuilder<Blocking, WithEnv, WithOutput>>,
+    ),
 
     UnsetEnv(EnvVariable),
     ReadIntoEnv(EnvVariable),
+    /// Only marks the keybinding, so executing it does nothing.
+    NoQueue,
+    ChangeDir(String),
+    Confirm(String),
 }
 
 // TODO: add support for goto nth line
@@ -86,11 +146,218 @@ pub enum SelectOperation {
     UnselectAll,
 }
 
+/// A duration with a unit, e.g. `500ms`, `5s`, `2m` or `1h`.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+pub struct Timeout(Duration);
+
+impl str::FromStr for Timeout {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let unit_start = s
+            .find(|c: char| c.is_ascii_alphabetic())
+            .with_context(|| format!("Missing unit (ms, s, m or h) in timeout: {}", s))?;
+        let (number, unit) = s.split_at(unit_start);
+        let number: f64 = number
+            .parse()
+            .with_context(|| format!("Invalid number in timeout: {}", s))?;
+        let secs = match unit {
+            "ms" => number / 1000.0,
+            "s" => number,
+            "m" => number * 60.0,
+            "h" => number * 60.0 * 60.0,
+            _ => bail!("Invalid unit (expected ms, s, m or h) in timeout: {}", s),
+        };
+        Duration::try_from_secs_f64(secs)
+            .map(Self)
+            .with_context(|| format!("Invalid timeout: {}", s))
+    }
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}s", self.0.as_secs_f64())
+    }
+}
+
+impl OperationParsed {
+    /// Parse an operation, explaining why it is invalid if so. The derived
+    /// parser only reports that parsing failed, so the arguments of the
+    /// operations are parsed again to find the actual error.
+    pub fn parse_explained(s: &str) -> Result<Self> {
+        let parse_error = match s.parse() {
+            Ok(operation) => return Ok(operation),
+            Err(e) => anyhow::Error::new(e),
+        };
+        let arguments = s.split_once(" -- ").map_or(s, |(arguments, _)| arguments);
+        let words: Vec<&str> = arguments.split(' ').collect();
+        let explanation = match words.as_slice() {
+            ["exec", "--timeout", timeout] => timeout.parse::<Timeout>().err(),
+            ["set-env" | "unset-env" | "read-into-env" | "choose", env_variable] => {
+                env_variable.parse::<EnvVariable>().err()
+            }
+            ["cursor", "down" | "up", steps] | ["scroll-left" | "scroll-right", steps] => steps
+                .parse::<usize>()
+                .with_context(|| format!("Invalid number of steps: {}", steps))
+                .err(),
+            _ => None,
+        };
+        Err(explanation
+            .unwrap_or(parse_error)
+            .context(format!("Invalid operation: \"{}\"", s)))
+    }
+
+    /// An example of every operation along with its description, which are
+    /// used to generate the documentation. Displaying the examples keeps
+    /// their syntax in sync with the parser.
+    pub fn documented() -> Vec<(Self, &'static str)> {
+        iter::successors(Self::next_documented(None), |operation| {
+            Self::next_documented(Some(operation))
+        })
+        .map(|operation| {
+            let description = operation.description();
+            (operation, description)
+        })
+        .collect()
+    }
+
+    /// The example documented after `previous`, or the first one if
+    /// `previous` is `None`. Exhaustively matched, so that new operations
+    /// can't be added without documenting them.
+    fn next_documented(previous: Option<&Self>) -> Option<Self> {
+        let command = || "echo $line".to_owned();
+        let env_variable = || "name".parse().expect("valid env variable");
+        let next = match previous {
+            None => Self::Exit,
+            Some(Self::Exit) => Self::Reload,
+            Some(Self::Reload) => Self::MoveCursor(MoveCursor::Down(1)),
+            Some(Self::MoveCursor(MoveCursor::Down(_))) => Self::MoveCursor(MoveCursor::Up(1)),
+            Some(Self::MoveCursor(MoveCursor::Up(_))) => Self::MoveCursor(MoveCursor::First),
+            Some(Self::MoveCursor(MoveCursor::First)) => Self::MoveCursor(MoveCursor::Last),
+            Some(Self::MoveCursor(MoveCursor::Last)) => Self::SelectLine(SelectOperation::Select),
+            Some(Self::SelectLine(SelectOperation::Select)) => {
+                Self::SelectLine(SelectOperation::Unselect)
+            }
+            Some(Self::SelectLine(SelectOperation::Unselect)) => {
+                Self::SelectLine(SelectOperation::ToggleSelection)
+            }
+            Some(Self::SelectLine(SelectOperation::ToggleSelection)) => {
+                Self::SelectLine(SelectOperation::SelectAll)
+            }
+            Some(Self::SelectLine(SelectOperation::SelectAll)) => {
+                Self::SelectLine(SelectOperation::UnselectAll)
+            }
+            Some(Self::SelectLine(SelectOperation::UnselectAll)) => Self::ScrollLeft(4),
+            Some(Self::ScrollLeft(_)) => Self::ScrollRight(4),
+            Some(Self::ScrollRight(_)) => Self::WrapLinesToggle,
+            Some(Self::WrapLinesToggle) => Self::ExecuteBlocking(command()),
+            Some(Self::ExecuteBlocking(_)) => {
+                Self::ExecuteBlockingWithTimeout("5s".parse().expect("valid timeout"), command())
+            }
+            Some(Self::ExecuteBlockingWithTimeout(_, _)) => {
+                Self::ExecuteBlockingWithStdin("sort".to_owned())
+            }
+            Some(Self::ExecuteBlockingWithStdin(_)) => {
+                Self::ExecuteBlockingWithNulSeparatedStdin("xargs -0 rm".to_owned())
+            }
+            Some(Self::ExecuteBlockingWithNulSeparatedStdin(_)) => {
+                Self::ExecuteNonBlocking(command())
+            }
+            Some(Self::ExecuteNonBlocking(_)) => Self::ExecuteEach(command()),
+            Some(Self::ExecuteEach(_)) => Self::ExecuteEachNonBlocking(command()),
+            Some(Self::ExecuteEachNonBlocking(_)) => Self::ExecuteDetached(command()),
+            Some(Self::ExecuteDetached(_)) => Self::SetEnv(env_variable(), command()),
+            Some(Self::SetEnv(_, _)) => Self::Choose(env_variable(), "ls".to_owned()),
+            Some(Self::Choose(_, _)) => Self::UnsetEnv(env_variable()),
+            Some(Self::UnsetEnv(_)) => Self::ReadIntoEnv(env_variable()),
+            Some(Self::ReadIntoEnv(_)) => Self::NoQueue,
+            Some(Self::NoQueue) => Self::ChangeDir("$line".to_owned()),
+            Some(Self::ChangeDir(_)) => Self::Confirm("Delete $lines_count lines?".to_owned()),
+            Some(Self::Confirm(_)) => Self::HelpShow,
+            Some(Self::HelpShow) => Self::HelpHide,
+            Some(Self::HelpHide) => Self::HelpToggle,
+            Some(Self::HelpToggle) => return None,
+        };
+        Some(next)
+    }
+
+    /// The description of an operation. Exhaustively matched, so that new
+    /// operations can't be left without a description.
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Exit => "Quit watchbind",
+            Self::Reload => "Reload the watched command manually, resets interval timer",
+            Self::MoveCursor(MoveCursor::Down(_)) => "Move cursor down N number of lines",
+            Self::MoveCursor(MoveCursor::Up(_)) => "Move cursor up N number of lines",
+            Self::MoveCursor(MoveCursor::First) => "Move cursor to the first line",
+            Self::MoveCursor(MoveCursor::Last) => "Move cursor to the last line",
+            Self::SelectLine(SelectOperation::Select) => "Select line that cursor is currently on",
+            Self::SelectLine(SelectOperation::Unselect) => {
+                "Unselect line that cursor is currently on"
+            }
+            Self::SelectLine(SelectOperation::ToggleSelection) => {
+                "Toggle selection of line that cursor is currently on"
+            }
+            Self::SelectLine(SelectOperation::SelectAll) => "Select all lines",
+            Self::SelectLine(SelectOperation::UnselectAll) => {
+                "Unselect all currently selected lines"
+            }
+            Self::ScrollLeft(_) => "Scroll the lines left by N characters",
+            Self::ScrollRight(_) => "Scroll the lines right by N characters",
+            Self::WrapLinesToggle => {
+                "Toggle whether lines wider than the screen are wrapped over multiple rows"
+            }
+            Self::ExecuteBlocking(_) => "Execute CMD and block until termination",
+            Self::ExecuteBlockingWithTimeout(_, _) => {
+                "Execute CMD and block until termination, or terminate it after the timeout"
+            }
+            Self::ExecuteBlockingWithStdin(_) => {
+                "Execute CMD with the selected lines written to its stdin, and block until termination"
+            }
+            Self::ExecuteBlockingWithNulSeparatedStdin(_) => {
+                "Execute CMD with the NUL-separated selected lines written to its stdin, and block until termination"
+            }
+            Self::ExecuteNonBlocking(_) => {
+                "Execute CMD as background process, i.e. don't block until command terminates"
+            }
+            Self::ExecuteEach(_) => {
+                "Execute CMD once for every selected line (set as line), and block until all terminated"
+            }
+            Self::ExecuteEachNonBlocking(_) => {
+                "Execute CMD once for every selected line (set as line) in the background"
+            }
+            Self::ExecuteDetached(_) => {
+                "Execute CMD as background process that keeps running after watchbind exits"
+            }
+            Self::SetEnv(_, _) => {
+                "Blockingly execute CMD, and save its output to the environment variable ENV"
+            }
+            Self::Choose(_, _) => {
+                "Blockingly execute CMD, and save the output lines chosen from a filterable list to the environment variable ENV"
+            }
+            Self::UnsetEnv(_) => "Unset the environment variable ENV",
+            Self::ReadIntoEnv(_) => "Read user input into the environment variable ENV",
+            Self::NoQueue => {
+                "Don't queue this key when pressed while blocking (if queue-keys is enabled)"
+            }
+            Self::ChangeDir(_) => {
+                "Change the working directory of all future commands (including the watched command) to PATH"
+            }
+            Self::Confirm(_) => {
+                "Ask for confirmation with MESSAGE, and skip the remaining operations if declined"
+            }
+            Self::HelpShow => "Show the help menu that shows all activated keybindings",
+            Self::HelpHide => "Hide the help menu",
+            Self::HelpToggle => "Toggle the help menu",
+        }
+    }
+}
+
 impl Operation {
     pub async fn execute(
         &self,
         state: &mut State,
         event_tx: &Sender<Event>,
+        background_jobs: &mut BackgroundJobs,
     ) -> Result<RequestedAction> {
         match self {
             Self::MoveCursor(MoveCursor::Down(steps)) => state.move_down(*steps),
@@ -102,6 +369,9 @@ impl Operation {
             Self::SelectLine(SelectOperation::ToggleSelection) => state.toggle_selection(),
             Self::SelectLine(SelectOperation::SelectAll) => state.select_all(),
             Self::SelectLine(SelectOperation::UnselectAll) => state.unselect_all(),
+            Self::ScrollLeft(steps) => state.scroll_left(*steps),
+            Self::ScrollRight(steps) => state.scroll_right(*steps),
+            Self::WrapLinesToggle => state.toggle_wrap_lines(),
             Self::HelpShow => state.show_help_menu().await,
             Self::HelpHide => state.hide_help_menu(),
             Self::HelpToggle => state.toggle_help_menu().await,
@@ -109,22 +379,62 @@ impl Operation {
             Self::Exit => return Ok(RequestedAction::Exit),
             Self::ExecuteNonBlocking(non_blocking_cmd) => {
                 state.add_lines_to_env().await?;
+                background_jobs.add(non_blocking_cmd.execute().await?);
+            }
+            Self::ExecuteDetached(non_blocking_cmd) => {
+                state.add_lines_to_env().await?;
+                // The process group isn't saved, so it isn't terminated on exit.
                 non_blocking_cmd.execute().await?;
             }
             Self::ExecuteBlocking(blocking_cmd) => {
                 state.add_lines_to_env().await?;
+                return Ok(execute_blocking(blocking_cmd, None, event_tx));
+            }
+            Self::ExecuteEach(blocking_cmd) => {
+                // Every execution sets `line` to its line instead.
+                state.remove_selected_lines_from_env().await?;
+                let lines = state.get_selected_lines_list();
 
-                // TODO: these clones are preventable by using Arc<> (I think Arc<Mutex> isn't required because executing them doesn't mutate them)
                 let blocking_cmd = blocking_cmd.clone();
                 let event_tx = event_tx.clone();
+                let (cancel_handle, cancel_signal) = cancel_channel();
                 tokio::spawn(async move {
-                    let result = blocking_cmd.execute().await;
+                    let result = blocking_cmd.execute_each(lines, cancel_signal).await;
 
                     // Ignore whether the sender has closed channel.
+                    let result = match result {
+                        Ok(summary) => {
+                            let _ = event_tx.send(Event::ExecEachCompleted(summary)).await;
+                            Ok(())
+                        }
+                        Err(e) => Err(e),
+                    };
                     let _ = event_tx.send(Event::SubcommandCompleted(result)).await;
                 });
 
-                return Ok(RequestedAction::ExecutingBlockingSubcommand);
+                return Ok(RequestedAction::ExecutingBlockingSubcommand(cancel_handle));
+            }
+            Self::ExecuteEachNonBlocking(blocking_cmd) => {
+                state.remove_selected_lines_from_env().await?;
+                let lines = state.get_selected_lines_list();
+
+                let blocking_cmd = blocking_cmd.clone();
+                let event_tx = event_tx.clone();
+                let (cancel_handle, cancel_signal) = cancel_channel();
+                let join_handle = tokio::spawn(async move {
+                    // Executions are only cancelled on exit, where the
+                    // summary isn't of interest anymore.
+                    if let Ok(summary) = blocking_cmd.execute_each(lines, cancel_signal).await {
+                        // Ignore whether the sender has closed channel.
+                        let _ = event_tx.send(Event::ExecEachCompleted(summary)).await;
+                    }
+                });
+                background_jobs.add_task(cancel_handle, join_handle);
+            }
+            Self::ExecuteBlockingWithStdin(blocking_cmd, separator) => {
+                state.add_cursor_line_to_env().await?;
+                let input = state.get_selected_lines_input(*separator);
+                return Ok(execute_blocking(blocking_cmd, Some(input), event_tx));
             }
             Self::SetEnv(env_variable, blocking_cmd) => {
                 state.add_lines_to_env().await?;
@@ -133,8 +443,9 @@ impl Operation {
                 let blocking_cmd = blocking_cmd.clone();
                 let env_variable = env_variable.clone();
                 let event_tx = event_tx.clone();
+                let (cancel_handle, cancel_signal) = cancel_channel();
                 tokio::spawn(async move {
-                    let result = blocking_cmd.execute().await.map(|output| {
+                    let result = blocking_cmd.execute(cancel_signal).await.map(|output| {
                         [(env_variable, output)]
                             .into_iter()
                             .collect::<EnvVariables>()
@@ -146,16 +457,54 @@ impl Operation {
                         .await;
                 });
 
-                return Ok(RequestedAction::ExecutingBlockingSubcommandForEnv);
+                return Ok(RequestedAction::ExecutingBlockingSubcommandForEnv(
+                    cancel_handle,
+                ));
+            }
+            Self::Choose(env_variable, blocking_cmd) => {
+                state.add_lines_to_env().await?;
+
+                let blocking_cmd = blocking_cmd.clone();
+                let env_variable = env_variable.clone();
+                let event_tx = event_tx.clone();
+                let (cancel_handle, cancel_signal) = cancel_channel();
+                tokio::spawn(async move {
+                    let result = blocking_cmd.execute(cancel_signal).await;
+
+                    // Ignore whether the sender has closed channel.
+                    let _ = event_tx
+                        .send(Event::SubcommandForChoiceCompleted(env_variable, result))
+                        .await;
+                });
+
+                return Ok(RequestedAction::ExecutingBlockingSubcommandForChoice(
+                    cancel_handle,
+                ));
             }
             Self::UnsetEnv(env) => state.unset_env(env).await,
             Self::ReadIntoEnv(env) => state.read_into_env(env).await,
+            Self::NoQueue => {}
+            Self::Confirm(message) => {
+                state.add_lines_to_env().await?;
+                state.show_confirmation(message).await;
+                return Ok(RequestedAction::Confirming);
+            }
+            Self::ChangeDir(path) => {
+                state.add_lines_to_env().await?;
+                if let Err(e) = state.change_working_dir(path).await {
+                    state.show_error(ErrorSource::Subcommand, format!("{:#}", e));
+                }
+            }
         };
         Ok(RequestedAction::Continue)
     }
 
     /// Convert the parsed form into the normal, runtime Operation form.
-    pub fn from_parsed(parsed: OperationParsed, env_variables: &Arc<Mutex<EnvVariables>>) -> Self {
+    pub fn from_parsed(
+        parsed: OperationParsed,
+        env_variables: &Arc<Mutex<EnvVariables>>,
+        settings: &CommandSettings,
+    ) -> Self {
         match parsed {
             OperationParsed::Exit => Self::Exit,
             OperationParsed::Reload => Self::Reload,
@@ -164,18 +513,71 @@ impl Operation {
             OperationParsed::HelpToggle => Self::HelpToggle,
             OperationParsed::MoveCursor(x) => Self::MoveCursor(x),
             OperationParsed::SelectLine(x) => Self::SelectLine(x),
+            OperationParsed::ScrollLeft(x) => Self::ScrollLeft(x),
+            OperationParsed::ScrollRight(x) => Self::ScrollRight(x),
+            OperationParsed::WrapLinesToggle => Self::WrapLinesToggle,
             OperationParsed::ExecuteBlocking(cmd) => Self::ExecuteBlocking(Arc::new(
-                CommandBuilder::new(cmd)
+                CommandBuilder::new(cmd, settings.clone())
                     .blocking()
                     .with_env(env_variables.clone()),
             )),
+            OperationParsed::ExecuteBlockingWithTimeout(Timeout(timeout), cmd) => {
+                Self::ExecuteBlocking(Arc::new(
+                    CommandBuilder::new(cmd, settings.clone())
+                        .with_timeout(timeout)
+                        .blocking()
+                        .with_env(env_variables.clone()),
+                ))
+            }
+            OperationParsed::ExecuteBlockingWithStdin(cmd) => Self::ExecuteBlockingWithStdin(
+                Arc::new(
+                    CommandBuilder::new(cmd, settings.clone())
+                        .with_stdin()
+                        .blocking()
+                        .with_env(env_variables.clone()),
+                ),
+                '\n',
+            ),
+            OperationParsed::ExecuteBlockingWithNulSeparatedStdin(cmd) => {
+                Self::ExecuteBlockingWithStdin(
+                    Arc::new(
+                        CommandBuilder::new(cmd, settings.clone())
+                            .with_stdin()
+                            .blocking()
+                            .with_env(env_variables.clone()),
+                    ),
+                    '\0',
+                )
+            }
             OperationParsed::ExecuteNonBlocking(cmd) => Self::ExecuteNonBlocking(Arc::new(
-                CommandBuilder::new(cmd).with_env(env_variables.clone()),
+                CommandBuilder::new(cmd, settings.clone()).with_env(env_variables.clone()),
+            )),
+            OperationParsed::ExecuteEach(cmd) => Self::ExecuteEach(Arc::new(
+                CommandBuilder::new(cmd, settings.clone())
+                    .blocking()
+                    .with_env(env_variables.clone()),
+            )),
+            OperationParsed::ExecuteEachNonBlocking(cmd) => Self::ExecuteEachNonBlocking(Arc::new(
+                CommandBuilder::new(cmd, settings.clone())
+                    .blocking()
+                    .with_env(env_variables.clone()),
+            )),
+            OperationParsed::ExecuteDetached(cmd) => Self::ExecuteDetached(Arc::new(
+                CommandBuilder::new(cmd, settings.clone()).with_env(env_variables.clone()),
             )),
             OperationParsed::SetEnv(env_var, cmd) => Self::SetEnv(
                 env_var,
                 Arc::new(
-                    CommandBuilder::new(cmd)
+                    CommandBuilder::new(cmd, settings.clone())
+                        .blocking()
+                        .with_output()
+                        .with_env(env_variables.clone()),
+                ),
+            ),
+            OperationParsed::Choose(env_var, cmd) => Self::Choose(
+                env_var,
+                Arc::new(
+                    CommandBuilder::new(cmd, settings.clone())
                         .blocking()
                         .with_output()
                         .with_env(env_variables.clone()),
@@ -183,10 +585,34 @@ impl Operation {
             ),
             OperationParsed::UnsetEnv(x) => Self::UnsetEnv(x),
             OperationParsed::ReadIntoEnv(x) => Self::ReadIntoEnv(x),
+            OperationParsed::NoQueue => Self::NoQueue,
+            OperationParsed::ChangeDir(path) => Self::ChangeDir(path),
+            OperationParsed::Confirm(message) => Self::Confirm(message),
         }
     }
 }
 
+/// Execute the blocking command in the background, with the input written to
+/// its stdin, and report its completion as an event.
+fn execute_blocking(
+    blocking_cmd: &Arc<CommandBuilder<Blocking, WithEnv>>,
+    input: Option<String>,
+    event_tx: &Sender<Event>,
+) -> RequestedAction {
+    // TODO: these clones are preventable by using Arc<> (I think Arc<Mutex> isn't required because executing them doesn't mutate them)
+    let blocking_cmd = blocking_cmd.clone();
+    let event_tx = event_tx.clone();
+    let (cancel_handle, cancel_signal) = cancel_channel();
+    tokio::spawn(async move {
+        let result = blocking_cmd.execute(input, cancel_signal).await;
+
+        // Ignore whether the sender has closed channel.
+        let _ = event_tx.send(Event::SubcommandCompleted(result)).await;
+    });
+
+    RequestedAction::ExecutingBlockingSubcommand(cancel_handle)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,9 +629,105 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_scroll() {
+        assert!(matches!(
+            "scroll-left 4".parse(),
+            Ok(OperationParsed::ScrollLeft(4))
+        ));
+        assert!(matches!(
+            "scroll-right 8".parse(),
+            Ok(OperationParsed::ScrollRight(8))
+        ));
+        assert!(matches!(
+            "wrap-lines-toggle".parse(),
+            Ok(OperationParsed::WrapLinesToggle)
+        ));
+    }
+
+    #[test]
+    fn test_parse_exec_with_timeout() {
+        let timeout = |secs| Timeout(Duration::from_secs_f64(secs));
+        assert!(
+            "exec --timeout 500ms -- sleep 1 -- x"
+                .parse::<OperationParsed>()
+                .ok()
+                == Some(OperationParsed::ExecuteBlockingWithTimeout(
+                    timeout(0.5),
+                    "sleep 1 -- x".to_owned()
+                ))
+        );
+        assert!(
+            "exec --timeout 2m -- ls".parse::<OperationParsed>().ok()
+                == Some(OperationParsed::ExecuteBlockingWithTimeout(
+                    timeout(120.0),
+                    "ls".to_owned()
+                ))
+        );
+        assert_eq!(timeout(0.5).to_string(), "0.5s");
+        assert!("exec --timeout 5 -- ls".parse::<OperationParsed>().is_err());
+        assert!("exec --timeout 5d -- ls"
+            .parse::<OperationParsed>()
+            .is_err());
+        assert!("exec --timeout -5s -- ls"
+            .parse::<OperationParsed>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_exec_with_stdin() {
+        assert!(
+            "exec --stdin -- sort -- x".parse::<OperationParsed>().ok()
+                == Some(OperationParsed::ExecuteBlockingWithStdin(
+                    "sort -- x".to_owned()
+                ))
+        );
+        assert!(
+            "exec --stdin --null -- xargs -0 rm"
+                .parse::<OperationParsed>()
+                .ok()
+                == Some(OperationParsed::ExecuteBlockingWithNulSeparatedStdin(
+                    "xargs -0 rm".to_owned()
+                ))
+        );
+    }
+
+    #[test]
+    fn test_parse_choose() {
+        assert!(
+            "choose namespace -- kubectl get ns -o name"
+                .parse::<OperationParsed>()
+                .ok()
+                == Some(OperationParsed::Choose(
+                    "namespace".parse().expect("valid env variable"),
+                    "kubectl get ns -o name".to_owned()
+                ))
+        );
+    }
+
+    #[test]
+    fn test_documented_operations_parse() {
+        for (operation, _) in OperationParsed::documented() {
+            assert!(operation.to_string().parse::<OperationParsed>().ok() == Some(operation));
+        }
+    }
+
     #[test]
     fn test_parse_move_cursor_invalid_step_size() {
         assert!("cursor down -42".parse::<OperationParsed>().is_err());
         assert!("cursor up -24".parse::<OperationParsed>().is_err());
     }
+
+    #[test]
+    fn test_parse_explained() {
+        let explanation = |s| {
+            let error = OperationParsed::parse_explained(s).err().expect("invalid");
+            format!("{:#}", error)
+        };
+        assert!(explanation("exec --timeout 5 -- ls").contains("Missing unit"));
+        assert!(explanation("set-env NAME -- ls").contains("uppercase"));
+        assert!(explanation("cursor down -42").contains("Invalid number of steps"));
+        assert!(explanation("fly away").contains("parse failed"));
+        assert!(OperationParsed::parse_explained("exec -- ls").is_ok());
+    }
 }
@@ -0,0 +1,92 @@
+use super::{
+    keybindings::{KeyEvent, OperationParsed},
+    print_to_stdout, ClapConfig,
+};
+use anyhow::Result;
+use clap::CommandFactory;
+use clap_mangen::{
+    roff::{bold, italic, roman, Roff},
+    Man,
+};
+use itertools::Itertools;
+
+/// Print the man page, which documents the command line options, as well as
+/// all keys and operations that can be used in keybindings.
+pub fn print_man_page() -> Result<()> {
+    print_to_stdout(&man_page()?)
+}
+
+fn man_page() -> Result<Vec<u8>> {
+    let mut man_page = vec![];
+    Man::new(ClapConfig::command()).render(&mut man_page)?;
+    man_page.extend(keybindings_sections().to_roff().as_bytes());
+    Ok(man_page)
+}
+
+fn keybindings_sections() -> Roff {
+    let mut roff = Roff::new();
+
+    roff.control("SH", ["KEYS"]);
+    roff.text([
+        roman("A key is either "),
+        italic("CODE"),
+        roman(" or "),
+        italic("MODIFIER"),
+        roman("+"),
+        italic("CODE"),
+        roman("."),
+    ]);
+    roff.control("TP", []);
+    roff.text([italic("MODIFIER")]);
+    roff.text([roman(KeyEvent::documented_modifiers().join(", "))]);
+    roff.control("TP", []);
+    roff.text([italic("CODE")]);
+    roff.text([roman(format!(
+        "{}, or any single character",
+        KeyEvent::documented_codes().iter().join(", ")
+    ))]);
+
+    roff.control("SH", ["OPERATIONS"]);
+    roff.text([roman(
+        "All operations that can be bound to keys, with example arguments. \
+         CMD is executed in a subshell, and ENV is the name of an environment \
+         variable.",
+    )]);
+    for (operation, description) in OperationParsed::documented() {
+        roff.control("TP", []);
+        roff.text([bold(operation.to_string())]);
+        roff.text([roman(description)]);
+    }
+
+    roff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_man_page_documents_keybindings() -> Result<()> {
+        // Undo roff's escaping of dashes to compare with the displayed names.
+        let man_page = String::from_utf8(man_page()?)?.replace("\\-", "-");
+
+        for name in KeyEvent::documented_modifiers()
+            .into_iter()
+            .chain(KeyEvent::documented_codes())
+        {
+            assert!(man_page.contains(&name), "undocumented key: {}", name);
+        }
+        assert!(man_page.contains("f12"));
+
+        for (operation, description) in OperationParsed::documented() {
+            let operation = operation.to_string();
+            assert!(
+                man_page.contains(&operation),
+                "undocumented operation: {}",
+                operation
+            );
+            assert!(man_page.contains(description));
+        }
+        Ok(())
+    }
+}
@@ -1,60 +1,231 @@
+mod check;
+mod columns;
 mod fields;
 mod keybindings;
+mod man;
+mod shell;
 mod style;
 
+pub use columns::Columns;
 pub use fields::{Fields, TableFormatter};
-pub use keybindings::{
-    KeyEvent, Keybindings, Operation, OperationParsed, Operations, OperationsParsed,
-};
+pub use keybindings::{KeyEvent, Keybindings, OperationParsed, OperationsParsed};
+pub use shell::Shell;
 pub use style::Styles;
 
+use self::columns::TomlColumn;
 use self::fields::{FieldSelections, FieldSeparator};
-use self::keybindings::{KeybindingsParsed, StringKeybindings};
+use self::keybindings::{ClapKeybindings, KeybindingsParsed, StringKeybindings};
+use crate::command::{CommandSettings, WorkingDir};
 use anyhow::{bail, Context, Result};
-use clap::Parser;
+use clap::{CommandFactory, Parser, Subcommand};
 use indoc::indoc;
-use serde::Deserialize;
-use std::{fs::read_to_string, path::PathBuf, time::Duration};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fs::read_to_string,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 pub struct Config {
     pub log_file: Option<PathBuf>,
+    /// All config files the config was read from, including the included
+    /// ones and the global config file, which are watched for changes.
+    pub config_files: Vec<PathBuf>,
     pub watched_command: String,
+    /// The initial working directory of all commands.
+    pub working_dir: Option<PathBuf>,
+    pub shell: Shell,
     pub watch_rate: Duration,
+    /// How long to wait for commands to terminate, before killing them.
+    pub grace_period: Duration,
+    /// Blocking commands that take longer are terminated.
+    pub command_timeout: Option<Duration>,
+    /// How many executions of `exec-each` operations run at the same time.
+    pub exec_each_concurrency: usize,
+    /// The key that cancels a running blocking operation.
+    pub cancel_key: KeyEvent,
+    /// Whether keys pressed while blocking are handled afterwards.
+    pub queue_keys: bool,
+    /// The maximum number of keys that are queued while blocking.
+    pub max_queued_keys: usize,
     pub styles: Styles,
     pub keybindings_parsed: KeybindingsParsed,
     pub header_lines: usize,
+    pub footer_lines: usize,
     pub fields: Fields,
+    pub columns: Columns,
+    pub frozen_columns: usize,
+    pub wrap_lines: bool,
     // pub initial_env_variables: Vec<String>,
     pub initial_env_variables: OperationsParsed,
 }
 
 impl Config {
-    pub fn parse() -> Result<Self> {
-        let cli = ClapConfig::parse();
-        let config_file = cli.config_file.clone();
-        let cli: TomlConfig = cli.into();
-        let config = match &config_file {
-            Some(path) => cli.merge(TomlConfig::parse(path)?),
-            None => cli,
-        };
-        config.try_into()
+    /// Parse the config from all config layers, falling back to the
+    /// defaults.
+    pub fn from_cli(cli: ClapConfig) -> Result<Self> {
+        Self::from_cli_collecting_files(cli, &mut vec![])
+    }
+
+    /// Like `from_cli`, but also collects the paths of all config files that
+    /// were read into `config_files`, even if the config is invalid, so that
+    /// they can still be watched for a fix.
+    pub fn from_cli_collecting_files(
+        cli: ClapConfig,
+        config_files: &mut Vec<PathBuf>,
+    ) -> Result<Self> {
+        let mut config: Self =
+            merge_layers(cli, user_global_config_file(), config_files)?.try_into()?;
+        config.config_files = config_files.clone();
+        Ok(config)
+    }
+
+    /// The settings that apply to the execution of all commands.
+    pub fn command_settings(&self) -> CommandSettings {
+        CommandSettings {
+            shell: self.shell.clone(),
+            timeout: self.command_timeout,
+            grace_period: self.grace_period,
+            working_dir: WorkingDir::new(self.working_dir.clone()),
+            exec_each_concurrency: self.exec_each_concurrency,
+        }
+    }
+
+    /// Print the settings in effect, i.e. all config layers (including the
+    /// defaults) merged, as TOML.
+    pub fn print(cli: ClapConfig) -> Result<()> {
+        let mut config =
+            merge_layers(cli, user_global_config_file(), &mut vec![])?.merge_defaults();
+        // Profiles that weren't selected aren't in effect.
+        config.profiles = None;
+        print!(
+            "{}",
+            toml::to_string(&config).context("Failed to serialize config as TOML")?
+        );
+        Ok(())
     }
 }
 
+/// Merge all config layers except for the defaults, where each layer is
+/// favored over the next: CLI options, `WATCHBIND_*` environment variables
+/// (both parsed by clap), the config file and the global config file. If a
+/// profile is selected, it is favored over the config files it is defined in.
+/// The paths of all config files that are read are collected into
+/// `config_files`, including the global config file if it doesn't exist yet.
+fn merge_layers(
+    cli: ClapConfig,
+    global_config_file: Option<PathBuf>,
+    config_files: &mut Vec<PathBuf>,
+) -> Result<TomlConfig> {
+    let config_file = cli.config_file.clone();
+    let no_default_config = cli.no_default_config;
+    let profile = cli.profile.clone();
+
+    let mut file_configs = vec![];
+    if let Some(path) = &config_file {
+        file_configs.push(TomlConfig::parse_with_includes(
+            path,
+            &mut vec![],
+            config_files,
+        )?);
+    }
+    if !no_default_config {
+        if let Some(path) = global_config_file {
+            if path.is_file() {
+                file_configs.push(TomlConfig::parse_with_includes(
+                    &path,
+                    &mut vec![],
+                    config_files,
+                )?);
+            } else {
+                config_files.push(path);
+            }
+        }
+    }
+    let file_config = file_configs.into_iter().reduce(TomlConfig::merge);
+
+    let file_config = match (file_config, profile) {
+        (Some(file_config), Some(profile)) => Some(file_config.select_profile(&profile)?),
+        (None, Some(profile)) => {
+            bail!("Cannot select profile \"{profile}\" without a config file")
+        }
+        (file_config, None) => file_config,
+    };
+
+    let cli: TomlConfig = cli.into();
+    let config = match file_config {
+        Some(file_config) => cli.merge(file_config),
+        None => cli,
+    };
+    Ok(config)
+}
+
+/// The global config file, which is located at
+/// `$XDG_CONFIG_HOME/watchbind/config.toml`, or, if `$XDG_CONFIG_HOME` is not
+/// set, at `$HOME/.config/watchbind/config.toml`.
+fn global_config_file(
+    xdg_config_home: Option<OsString>,
+    home: Option<OsString>,
+) -> Option<PathBuf> {
+    let config_dir = match xdg_config_home.filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(home?).join(".config"),
+    };
+    Some(config_dir.join("watchbind").join("config.toml"))
+}
+
+/// The global config file of the current user's environment.
+fn user_global_config_file() -> Option<PathBuf> {
+    global_config_file(env::var_os("XDG_CONFIG_HOME"), env::var_os("HOME"))
+}
+
 impl TryFrom<TomlConfig> for Config {
     type Error = anyhow::Error;
     fn try_from(toml: TomlConfig) -> Result<Self, Self::Error> {
         let default = TomlConfig::default();
         Ok(Self {
             log_file: toml.log_file,
+            config_files: vec![],
             initial_env_variables: toml.initial_env_variables.unwrap_or_default().try_into()?,
             watched_command: match toml.watched_command {
                 Some(command) => command,
                 None => bail!("A command must be provided via command line or config file"),
             },
-            watch_rate: Duration::from_secs_f64(
+            working_dir: toml.working_dir.map(canonicalize_working_dir).transpose()?,
+            shell: toml.shell.or(default.shell).expect("default"),
+            watch_rate: secs_to_duration(
+                "interval",
                 toml.interval.or(default.interval).expect("default"),
-            ),
+            )?,
+            grace_period: secs_to_duration(
+                "grace-period",
+                toml.grace_period.or(default.grace_period).expect("default"),
+            )?,
+            command_timeout: toml
+                .command_timeout
+                .map(|secs| secs_to_duration("command-timeout", secs))
+                .transpose()?,
+            exec_each_concurrency: check_exec_each_concurrency(
+                toml.exec_each_concurrency
+                    .or(default.exec_each_concurrency)
+                    .expect("default"),
+            )?,
+            cancel_key: toml
+                .cancel_key
+                .or(default.cancel_key)
+                .expect("default")
+                .parse()
+                .context("Invalid cancel key")?,
+            queue_keys: toml.queue_keys.or(default.queue_keys).expect("default"),
+            max_queued_keys: toml
+                .max_queued_keys
+                .or(default.max_queued_keys)
+                .expect("default"),
             styles: Styles::parse(
                 toml.fg.or(default.fg),
                 toml.bg.or(default.bg),
@@ -67,18 +238,62 @@ impl TryFrom<TomlConfig> for Config {
                 toml.header_bold.or(default.header_bold),
                 toml.selected_bg.or(default.selected_bg),
             )?,
-            keybindings_parsed: StringKeybindings::merge(toml.keybindings, default.keybindings)
-                .expect("default")
-                .try_into()?,
+            keybindings_parsed: StringKeybindings::merge(
+                toml.keybindings,
+                match toml.no_default_keybindings {
+                    Some(true) => None,
+                    _ => default.keybindings,
+                },
+            )
+            .unwrap_or_default()
+            .try_into()?,
             header_lines: toml.header_lines.unwrap_or(0),
-            fields: Fields::try_new(toml.field_separator, toml.field_selections)?,
+            footer_lines: toml.footer_lines.unwrap_or(0),
+            fields: Fields::try_new(
+                toml.field_separator,
+                toml.field_selections,
+                toml.header_lines.unwrap_or(0),
+            )?,
+            columns: toml.columns.unwrap_or_default().try_into()?,
+            frozen_columns: toml.frozen_columns.unwrap_or(0),
+            wrap_lines: toml.wrap_lines.or(default.wrap_lines).expect("default"),
         })
     }
 }
 
-#[derive(Deserialize)]
+fn canonicalize_working_dir(dir: PathBuf) -> Result<PathBuf> {
+    dir.canonicalize()
+        .with_context(|| format!("Failed to find working directory: {}", dir.display()))
+}
+
+/// Convert a setting in seconds, which mustn't be negative.
+fn secs_to_duration(setting: &str, secs: f64) -> Result<Duration> {
+    Duration::try_from_secs_f64(secs)
+        .with_context(|| format!("{setting} must be a non-negative number of seconds"))
+}
+
+fn check_exec_each_concurrency(concurrency: usize) -> Result<usize> {
+    if concurrency == 0 {
+        bail!("The exec-each concurrency must be at least 1");
+    }
+    Ok(concurrency)
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct TomlConfig {
+    /// Config files whose settings are overridden by this config. Later
+    /// files override earlier ones.
+    include: Option<Vec<PathBuf>>,
+
+    /// A config file whose settings are overridden by this config and all
+    /// included configs.
+    extends: Option<PathBuf>,
+
+    /// Named configs, one of which can be selected to override this config.
+    #[serde(rename = "profile")]
+    profiles: Option<HashMap<String, TomlConfig>>,
+
     log_file: Option<PathBuf>,
 
     #[serde(rename = "initial-env")]
@@ -87,7 +302,44 @@ pub struct TomlConfig {
     #[serde(rename = "watched-command")]
     watched_command: Option<String>,
 
+    /// The directory in which the watched command and all other commands are
+    /// executed.
+    #[serde(rename = "working-dir")]
+    working_dir: Option<PathBuf>,
+
+    /// The shell program and its arguments that commands are appended to, or
+    /// "none" to execute commands directly.
+    shell: Option<Shell>,
+
     interval: Option<f64>,
+
+    /// Seconds to wait for terminated commands to exit, before killing them.
+    #[serde(rename = "grace-period")]
+    grace_period: Option<f64>,
+
+    /// Seconds after which the watched command and blocking subcommands are
+    /// terminated.
+    #[serde(rename = "command-timeout")]
+    command_timeout: Option<f64>,
+
+    /// How many executions of `exec-each` operations run at the same time.
+    #[serde(rename = "exec-each-concurrency")]
+    exec_each_concurrency: Option<usize>,
+
+    /// The key that cancels a running blocking operation.
+    #[serde(rename = "cancel-key")]
+    cancel_key: Option<String>,
+
+    /// Queue keys pressed while blocking, and handle them afterwards, instead
+    /// of discarding them.
+    #[serde(rename = "queue-keys")]
+    queue_keys: Option<bool>,
+
+    /// The maximum number of keys that are queued while blocking, so that a
+    /// long blocking operation isn't followed by an endless replay of keys.
+    #[serde(rename = "max-queued-keys")]
+    max_queued_keys: Option<usize>,
+
     fg: Option<String>,
     bg: Option<String>,
     bold: Option<bool>,
@@ -116,32 +368,162 @@ pub struct TomlConfig {
     #[serde(rename = "header-lines")]
     header_lines: Option<usize>,
 
+    #[serde(rename = "footer-lines")]
+    footer_lines: Option<usize>,
+
     #[serde(rename = "field-separator")]
     field_separator: Option<FieldSeparator>,
 
     #[serde(rename = "fields")]
     field_selections: Option<FieldSelections>,
 
+    columns: Option<Vec<TomlColumn>>,
+
+    #[serde(rename = "frozen-columns")]
+    frozen_columns: Option<usize>,
+
+    #[serde(rename = "wrap-lines")]
+    wrap_lines: Option<bool>,
+
+    /// Don't include the default keybindings.
+    #[serde(rename = "no-default-keybindings")]
+    no_default_keybindings: Option<bool>,
+
+    /// Keys whose keybindings from lower config layers (including the
+    /// defaults) are removed.
+    unbind: Option<Vec<String>>,
+
     keybindings: Option<StringKeybindings>,
 }
 
 impl TomlConfig {
-    fn parse(config_file: &str) -> Result<Self> {
-        let config = toml::from_str(
+    fn parse(config_file: &Path) -> Result<Self> {
+        Self::parse_with_includes(config_file, &mut vec![], &mut vec![])
+    }
+
+    /// Parse a config file, and merge it with all the config files it
+    /// includes or extends, whose paths (like the working directory) are
+    /// relative to the including file.
+    /// The files that are currently being included are tracked to detect
+    /// cyclic includes, and all files that are read are collected into
+    /// `config_files`.
+    fn parse_with_includes(
+        config_file: &Path,
+        including_files: &mut Vec<PathBuf>,
+        config_files: &mut Vec<PathBuf>,
+    ) -> Result<Self> {
+        if !config_files.iter().any(|path| path == config_file) {
+            config_files.push(config_file.to_path_buf());
+        }
+        let config_file_str = config_file.display();
+        let canonical_config_file = config_file
+            .canonicalize()
+            .with_context(|| format!("Failed to read configuration from {config_file_str}"))?;
+        if including_files.contains(&canonical_config_file) {
+            bail!("Cyclic include of config file {config_file_str}");
+        }
+
+        let mut config: Self = toml::from_str(
             &read_to_string(config_file)
-                .with_context(|| format!("Failed to read configuration from {config_file}"))?,
+                .with_context(|| format!("Failed to read configuration from {config_file_str}"))?,
         )
-        .with_context(|| format!("Failed to parse TOML from {config_file}"))?;
+        .with_context(|| format!("Failed to parse TOML from {config_file_str}"))?;
+        config.apply_unbind();
+
+        for (name, profile) in config.profiles.iter_mut().flatten() {
+            if profile.profiles.is_some() || profile.include.is_some() || profile.extends.is_some()
+            {
+                bail!("Profile \"{name}\" in {config_file_str} cannot contain profiles, includes or extends");
+            }
+            profile.apply_unbind();
+        }
+
+        let dir = canonical_config_file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        config.resolve_working_dir(&dir);
+        for profile in config.profiles.iter_mut().flat_map(HashMap::values_mut) {
+            profile.resolve_working_dir(&dir);
+        }
+        let includes = config.include.take().unwrap_or_default();
+        let extends = config.extends.take();
+
+        // Merge from the highest to the lowest priority.
+        including_files.push(canonical_config_file);
+        for path in includes.iter().rev().chain(&extends) {
+            let included =
+                Self::parse_with_includes(&dir.join(path), including_files, config_files)
+                    .with_context(|| {
+                        format!("Failed to include config file into {config_file_str}")
+                    })?;
+            config = config.merge(included);
+        }
+        including_files.pop();
+
         Ok(config)
     }
 
+    /// Make a relative working directory relative to the given directory of
+    /// the config file.
+    fn resolve_working_dir(&mut self, dir: &Path) {
+        if let Some(working_dir) = &mut self.working_dir {
+            *working_dir = dir.join(&*working_dir);
+        }
+    }
+
+    /// Unbinding a key is the same as binding it to no operations, which
+    /// overrides its keybindings in all lower config layers. Keybindings in
+    /// the same config are favored.
+    fn apply_unbind(&mut self) {
+        if let Some(keys) = self.unbind.take() {
+            let unbound = keys.into_iter().map(|key| (key, vec![])).collect_vec();
+            self.keybindings =
+                StringKeybindings::merge(self.keybindings.take(), Some(unbound.into()));
+        }
+    }
+
+    /// Merge with the defaults, which only include the default keybindings
+    /// if they aren't disabled.
+    fn merge_defaults(self) -> Self {
+        let mut default = Self::default();
+        if self.no_default_keybindings == Some(true) {
+            default.keybindings = None;
+        }
+        self.merge(default)
+    }
+
+    /// Override this config with the selected profile.
+    fn select_profile(mut self, name: &str) -> Result<Self> {
+        let mut profiles = self.profiles.take().unwrap_or_default();
+        let Some(profile) = profiles.remove(name) else {
+            bail!(
+                "Unknown profile \"{}\", available profiles: {}",
+                name,
+                profiles.keys().sorted().join(", ")
+            );
+        };
+        Ok(profile.merge(self))
+    }
+
     // self is favored
     fn merge(self, other: Self) -> Self {
         Self {
+            include: self.include.or(other.include),
+            extends: self.extends.or(other.extends),
+            profiles: merge_profiles(self.profiles, other.profiles),
             log_file: self.log_file.or(other.log_file),
             initial_env_variables: self.initial_env_variables.or(other.initial_env_variables),
             watched_command: self.watched_command.or(other.watched_command),
+            working_dir: self.working_dir.or(other.working_dir),
+            shell: self.shell.or(other.shell),
             interval: self.interval.or(other.interval),
+            grace_period: self.grace_period.or(other.grace_period),
+            command_timeout: self.command_timeout.or(other.command_timeout),
+            exec_each_concurrency: self.exec_each_concurrency.or(other.exec_each_concurrency),
+            cancel_key: self.cancel_key.or(other.cancel_key),
+            queue_keys: self.queue_keys.or(other.queue_keys),
+            max_queued_keys: self.max_queued_keys.or(other.max_queued_keys),
             fg: self.fg.or(other.fg),
             bg: self.bg.or(other.bg),
             bold: self.bold.or(other.bold),
@@ -153,20 +535,58 @@ impl TomlConfig {
             header_bold: self.header_bold.or(other.header_bold),
             selected_bg: self.selected_bg.or(other.selected_bg),
             header_lines: self.header_lines.or(other.header_lines),
+            footer_lines: self.footer_lines.or(other.footer_lines),
             field_separator: self.field_separator.or(other.field_separator),
             field_selections: self.field_selections.or(other.field_selections),
+            columns: self.columns.or(other.columns),
+            frozen_columns: self.frozen_columns.or(other.frozen_columns),
+            wrap_lines: self.wrap_lines.or(other.wrap_lines),
+            no_default_keybindings: self.no_default_keybindings.or(other.no_default_keybindings),
+            unbind: self.unbind.or(other.unbind),
             keybindings: StringKeybindings::merge(self.keybindings, other.keybindings),
         }
     }
 }
 
+/// Merge the profiles of two configs, where profiles with the same name are
+/// merged as well. The profiles in `new_opt` are favored.
+fn merge_profiles(
+    new_opt: Option<HashMap<String, TomlConfig>>,
+    old_opt: Option<HashMap<String, TomlConfig>>,
+) -> Option<HashMap<String, TomlConfig>> {
+    match (new_opt, old_opt) {
+        (Some(new), Some(mut merged)) => {
+            for (name, new_profile) in new {
+                let profile = match merged.remove(&name) {
+                    Some(old_profile) => new_profile.merge(old_profile),
+                    None => new_profile,
+                };
+                merged.insert(name, profile);
+            }
+            Some(merged)
+        }
+        (new_opt, old_opt) => new_opt.or(old_opt),
+    }
+}
+
 impl From<ClapConfig> for TomlConfig {
     fn from(clap: ClapConfig) -> Self {
         Self {
+            include: None,
+            extends: None,
+            profiles: None,
             log_file: clap.log_file,
             initial_env_variables: clap.initial_env_variables,
             watched_command: clap.watched_command.map(|s| s.join(" ")),
+            working_dir: clap.working_dir,
+            shell: clap.shell,
             interval: clap.interval,
+            grace_period: clap.grace_period,
+            command_timeout: clap.command_timeout,
+            exec_each_concurrency: clap.exec_each_concurrency,
+            cancel_key: clap.cancel_key,
+            queue_keys: clap.queue_keys,
+            max_queued_keys: clap.max_queued_keys,
             fg: clap.fg,
             bg: clap.bg,
             bold: clap.bold,
@@ -178,9 +598,17 @@ impl From<ClapConfig> for TomlConfig {
             header_bold: clap.header_bold,
             selected_bg: clap.selected_bg,
             header_lines: clap.header_lines,
+            footer_lines: clap.footer_lines,
             field_separator: clap.field_separator,
             field_selections: clap.field_selections,
-            keybindings: clap.keybindings.map(|vec| vec.into()),
+            columns: None,
+            frozen_columns: clap.frozen_columns,
+            wrap_lines: clap.wrap_lines,
+            no_default_keybindings: clap.no_default_keybindings.then_some(true),
+            unbind: None,
+            keybindings: clap
+                .keybindings
+                .map(|lists| lists.into_iter().flatten().collect::<Vec<_>>().into()),
         }
     }
 }
@@ -189,12 +617,19 @@ impl Default for TomlConfig {
     fn default() -> Self {
         let toml = indoc! {r#"
 			"interval" = 5.0
+			"grace-period" = 1.0
+			"exec-each-concurrency" = 1
+			"cancel-key" = "ctrl+c"
+			"queue-keys" = false
+			"max-queued-keys" = 16
+			"shell" = [ "sh", "-c" ]
 			"bold" = false
 			"cursor-fg" = "black"
 			"cursor-bg" = "blue"
 			"cursor-bold" = true
 			"header-fg" = "blue"
 			"selected-bg" = "magenta"
+			"wrap-lines" = false
 
 			[keybindings]
 			"ctrl+c" = [ "exit" ]
@@ -210,89 +645,663 @@ impl Default for TomlConfig {
 			"k" = [ "cursor up 1" ]
 			"g" = [ "cursor first" ]
 			"G" = [ "cursor last" ]
+			"left" = [ "scroll-left 4" ]
+			"right" = [ "scroll-right 4" ]
 		"#};
         toml::from_str(toml).expect("Default toml config file should be correct")
     }
 }
 
-#[derive(Parser)]
-#[clap(version, about)]
+#[derive(Parser, Clone)]
+#[clap(version, about, args_conflicts_with_subcommands = true)]
 pub struct ClapConfig {
+    #[command(subcommand)]
+    subcommand: Option<ClapSubcommand>,
+
     /// Enable logging, and write logs to file.
-    #[arg(short, long, value_name = "FILE")]
+    #[arg(short, long, value_name = "FILE", env = "WATCHBIND_LOG_FILE")]
     log_file: Option<PathBuf>,
 
     /// Command to watch by executing periodically
-    #[arg(long = "initial-env", value_name = "LIST", value_delimiter = ',')]
+    #[arg(
+        long = "initial-env",
+        value_name = "LIST",
+        value_delimiter = ',',
+        env = "WATCHBIND_INITIAL_ENV"
+    )]
     initial_env_variables: Option<Vec<String>>,
 
     /// Command to watch by executing periodically
-    #[arg(trailing_var_arg(true))]
+    #[arg(trailing_var_arg(true), env = "WATCHBIND_COMMAND")]
     watched_command: Option<Vec<String>>,
 
+    /// Directory in which the watched command and all other commands are executed
+    #[arg(
+        short = 'C',
+        long = "working-dir",
+        value_name = "DIR",
+        env = "WATCHBIND_WORKING_DIR"
+    )]
+    working_dir: Option<PathBuf>,
+
+    /// Shell program and arguments that commands are appended to, e.g., `bash -o pipefail -c`, or `none` to split commands into words and execute them directly
+    #[arg(long, value_name = "SHELL", env = "WATCHBIND_SHELL")]
+    shell: Option<Shell>,
+
     /// TOML config file path
-    #[arg(short, long, value_name = "FILE")]
-    config_file: Option<String>,
+    #[arg(short, long, value_name = "FILE", env = "WATCHBIND_CONFIG")]
+    config_file: Option<PathBuf>,
+
+    /// Select a profile, i.e. a `[profile.NAME]` table, from the config files
+    #[arg(short, long, value_name = "NAME", env = "WATCHBIND_PROFILE")]
+    profile: Option<String>,
+
+    /// Don't load the global config file `$XDG_CONFIG_HOME/watchbind/config.toml`
+    #[arg(long = "no-default-config", env = "WATCHBIND_NO_DEFAULT_CONFIG")]
+    no_default_config: bool,
+
+    /// Don't include the default keybindings
+    #[arg(
+        long = "no-default-keybindings",
+        env = "WATCHBIND_NO_DEFAULT_KEYBINDINGS"
+    )]
+    no_default_keybindings: bool,
+
+    /// Print the config in effect (all config layers merged) as TOML, and exit
+    #[arg(long = "print-config")]
+    print_config: bool,
 
     /// Seconds to wait between updates, 0 only executes once
-    #[arg(short, long, value_name = "SECS")]
+    #[arg(short, long, value_name = "SECS", env = "WATCHBIND_INTERVAL")]
     interval: Option<f64>,
 
+    /// Seconds to wait for commands to exit after terminating them (on reload and exit), before killing them
+    #[arg(
+        long = "grace-period",
+        value_name = "SECS",
+        env = "WATCHBIND_GRACE_PERIOD"
+    )]
+    grace_period: Option<f64>,
+
+    /// Seconds after which the watched command and blocking subcommands are terminated, and an error is displayed
+    #[arg(
+        long = "command-timeout",
+        value_name = "SECS",
+        env = "WATCHBIND_COMMAND_TIMEOUT"
+    )]
+    command_timeout: Option<f64>,
+
+    /// Number of executions of `exec-each` operations (one per selected line) that run at the same time
+    #[arg(
+        long = "exec-each-concurrency",
+        value_name = "N",
+        env = "WATCHBIND_EXEC_EACH_CONCURRENCY"
+    )]
+    exec_each_concurrency: Option<usize>,
+
+    /// Key that cancels a running blocking operation (reload, exec or set-env), and aborts its remaining operations
+    #[arg(long = "cancel-key", value_name = "KEY", env = "WATCHBIND_CANCEL_KEY")]
+    cancel_key: Option<String>,
+
+    /// Queue keys pressed while blocking (except for keybindings containing `no-queue`), and handle them afterwards, instead of discarding them
+    #[arg(long = "queue-keys", value_name = "BOOL", env = "WATCHBIND_QUEUE_KEYS")]
+    queue_keys: Option<bool>,
+
+    /// Maximum number of keys that are queued while blocking, if `queue-keys` is enabled
+    #[arg(
+        long = "max-queued-keys",
+        value_name = "N",
+        env = "WATCHBIND_MAX_QUEUED_KEYS"
+    )]
+    max_queued_keys: Option<usize>,
+
     /// Foreground color of all lines except cursor
-    #[arg(long, value_name = "COLOR")]
+    #[arg(long, value_name = "COLOR", env = "WATCHBIND_FG")]
     fg: Option<String>,
 
     /// Background color of all lines except cursor
-    #[arg(long, value_name = "COLOR")]
+    #[arg(long, value_name = "COLOR", env = "WATCHBIND_BG")]
     bg: Option<String>,
 
     /// Text on all lines except the cursor's line are bold
-    #[arg(long, value_name = "BOOL")]
+    #[arg(long, value_name = "BOOL", env = "WATCHBIND_BOLD")]
     bold: Option<bool>,
 
     /// Foreground color of cursor
-    #[arg(long = "cursor-fg", value_name = "COLOR")]
+    #[arg(long = "cursor-fg", value_name = "COLOR", env = "WATCHBIND_CURSOR_FG")]
     cursor_fg: Option<String>,
 
     /// Background color of cursor
-    #[arg(long = "cursor-bg", value_name = "COLOR")]
+    #[arg(long = "cursor-bg", value_name = "COLOR", env = "WATCHBIND_CURSOR_BG")]
     cursor_bg: Option<String>,
 
     /// Text on cursor's line is bold
-    #[arg(long = "cursor-bold", value_name = "BOOL")]
+    #[arg(
+        long = "cursor-bold",
+        value_name = "BOOL",
+        env = "WATCHBIND_CURSOR_BOLD"
+    )]
     cursor_bold: Option<bool>,
 
-    /// Foreground color of header lines
-    #[arg(long = "header-fg", value_name = "COLOR")]
+    /// Foreground color of header and footer lines
+    #[arg(long = "header-fg", value_name = "COLOR", env = "WATCHBIND_HEADER_FG")]
     header_fg: Option<String>,
 
-    /// Background color of header lines
-    #[arg(long = "header-bg", value_name = "COLOR")]
+    /// Background color of header and footer lines
+    #[arg(long = "header-bg", value_name = "COLOR", env = "WATCHBIND_HEADER_BG")]
     header_bg: Option<String>,
 
-    /// Text on header line is bold
-    #[arg(long = "header-bold", value_name = "BOOL")]
+    /// Text on header and footer lines is bold
+    #[arg(
+        long = "header-bold",
+        value_name = "BOOL",
+        env = "WATCHBIND_HEADER_BOLD"
+    )]
     header_bold: Option<bool>,
 
     /// Background color of selected line marker
-    #[arg(long = "selected-bg", value_name = "COLOR")]
+    #[arg(
+        long = "selected-bg",
+        value_name = "COLOR",
+        env = "WATCHBIND_SELECTED_BG"
+    )]
     selected_bg: Option<String>,
 
     /// The first N lines of the input are treated as a sticky header
-    #[arg(long = "header-lines", value_name = "N")]
+    #[arg(
+        long = "header-lines",
+        value_name = "N",
+        env = "WATCHBIND_HEADER_LINES"
+    )]
     header_lines: Option<usize>,
 
+    /// The last N lines of the input are treated as a sticky footer
+    #[arg(
+        long = "footer-lines",
+        value_name = "N",
+        env = "WATCHBIND_FOOTER_LINES"
+    )]
+    footer_lines: Option<usize>,
+
     /// Field separator [possible values: any string]
-    #[arg(short = 's', long = "field-separator", value_name = "STRING")]
+    #[arg(
+        short = 's',
+        long = "field-separator",
+        value_name = "STRING",
+        env = "WATCHBIND_FIELD_SEPARATOR"
+    )]
     field_separator: Option<FieldSeparator>,
 
-    /// Field selections/ranges (comma-separated), e.g., `X`, `X-Y`, `X-` (field indexes start at 1).
-    #[arg(short = 'f', long = "fields", value_name = "LIST")]
+    /// The first N displayed columns are not scrolled horizontally
+    #[arg(
+        long = "frozen-columns",
+        value_name = "N",
+        env = "WATCHBIND_FROZEN_COLUMNS"
+    )]
+    frozen_columns: Option<usize>,
+
+    /// Lines wider than the screen are wrapped over multiple rows
+    #[arg(long = "wrap-lines", value_name = "BOOL", env = "WATCHBIND_WRAP_LINES")]
+    wrap_lines: Option<bool>,
+
+    /// Field selections/ranges (comma-separated), e.g., `X`, `X-Y`, `X-` (field indexes start at 1, negative indexes count from the end, names refer to the first header line and are quoted with `"` if they contain `-` or `,`).
+    #[arg(
+        short = 'f',
+        long = "fields",
+        value_name = "LIST",
+        env = "WATCHBIND_FIELDS"
+    )]
     field_selections: Option<FieldSelections>,
 
     // TODO: replace with StringKeybindings once clap supports parsing into HashMap
-    // TODO: known clap bug: replace with ClapKeybindings once supported
-    /// Keybindings as comma-separated `KEY:OP[+OP]*` pairs, e.g., `q:select+exit,r:reload`.
-    #[arg(short = 'b', long = "bind", value_name = "LIST", value_delimiter = ',', value_parser = keybindings::parse_str)]
-    keybindings: Option<Vec<(String, Vec<String>)>>,
+    /// Keybindings as comma-separated `KEY:OP[+OP]*` pairs, e.g., `q:select+exit,r:reload`. Escape `,`, `:`, `+` and `\` with a backslash, or quote whole operations, e.g., `e:"exec -- echo a+b"`. `KEY:` unbinds a key.
+    #[arg(short = 'b', long = "bind", value_name = "LIST", value_parser = keybindings::parse_str, env = "WATCHBIND_BIND")]
+    keybindings: Option<Vec<ClapKeybindings>>,
+}
+
+impl ClapConfig {
+    pub fn print_config(&self) -> bool {
+        self.print_config
+    }
+
+    pub fn subcommand(&self) -> Option<&ClapSubcommand> {
+        self.subcommand.as_ref()
+    }
+}
+
+/// Subcommands are only recognized as the first argument, so a watched
+/// command with the same name has to be passed after `--`, e.g.
+/// `watchbind -- man ls`.
+#[derive(Subcommand, Clone)]
+pub enum ClapSubcommand {
+    /// Validate config files, reporting all errors and unreachable keybindings
+    Check {
+        /// TOML config file paths
+        #[arg(required = true, value_name = "FILE")]
+        config_files: Vec<PathBuf>,
+    },
+
+    /// Print shell completions
+    Completions {
+        #[arg(value_name = "SHELL")]
+        shell: clap_complete::Shell,
+    },
+
+    /// Print the man page
+    Man,
+}
+
+impl ClapSubcommand {
+    pub fn run(&self) -> Result<()> {
+        match self {
+            Self::Check { config_files } => check::check(config_files),
+            Self::Completions { shell } => {
+                let mut command = ClapConfig::command();
+                let name = command.get_name().to_owned();
+                // Generating directly into stdout panics if it is closed.
+                let mut completions = vec![];
+                clap_complete::generate(*shell, &mut command, name, &mut completions);
+                print_to_stdout(&completions)
+            }
+            Self::Man => man::print_man_page(),
+        }
+    }
+}
+
+/// Print to stdout, ignoring that it was closed early, e.g. when piping into
+/// `head`.
+fn print_to_stdout(bytes: &[u8]) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    match stdout.write_all(bytes).and_then(|_| stdout.flush()) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use clap::FromArgMatches;
+
+    /// Write the given config files into a new temporary directory.
+    fn write_config_files(test_name: &str, files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new(test_name);
+        for (name, content) in files {
+            dir.write(name, content);
+        }
+        dir
+    }
+
+    #[test]
+    fn test_include_and_extends() -> Result<()> {
+        let dir = write_config_files(
+            "include",
+            &[
+                (
+                    "base.toml",
+                    indoc! {r#"
+                        interval = 1.0
+                        fg = "red"
+                        [keybindings]
+                        "a" = [ "exit" ]
+                        "b" = [ "exit" ]
+                    "#},
+                ),
+                (
+                    "shared/common.toml",
+                    indoc! {r#"
+                        extends = "../base.toml"
+                        fg = "green"
+                        [keybindings]
+                        "b" = [ "reload" ]
+                    "#},
+                ),
+                (
+                    "config.toml",
+                    indoc! {r#"
+                        include = [ "shared/common.toml" ]
+                        interval = 2.0
+                        [keybindings]
+                        "c" = [ "reload" ]
+                    "#},
+                ),
+            ],
+        );
+
+        let config = TomlConfig::parse(&dir.path().join("config.toml"))?;
+        assert_eq!(config.interval, Some(2.0));
+        assert_eq!(config.fg.as_deref(), Some("green"));
+
+        let keybindings = config.keybindings.expect("keybindings are merged");
+        let keybindings = keybindings.as_ref();
+        assert_eq!(keybindings["a"], vec!["exit"]);
+        assert_eq!(keybindings["b"], vec!["reload"]);
+        assert_eq!(keybindings["c"], vec!["reload"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cyclic_include() -> Result<()> {
+        let dir = write_config_files(
+            "cyclic-include",
+            &[
+                ("a.toml", r#"include = [ "b.toml" ]"#),
+                ("b.toml", r#"extends = "a.toml""#),
+            ],
+        );
+
+        let error = TomlConfig::parse(&dir.path().join("a.toml"))
+            .err()
+            .expect("cycle");
+        assert!(format!("{error:#}").contains("Cyclic include"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_profile() -> Result<()> {
+        let config: TomlConfig = toml::from_str(indoc! {r#"
+            interval = 1.0
+            fg = "red"
+
+            [keybindings]
+            "a" = [ "exit" ]
+
+            [profile.pods]
+            watched-command = "kubectl get pods"
+            interval = 2.0
+            [profile.pods.keybindings]
+            "d" = [ "exec -- kubectl delete pod $field_name" ]
+
+            [profile.git]
+            watched-command = "git status --short"
+        "#})?;
+
+        let pods = config.select_profile("pods")?;
+        assert_eq!(pods.watched_command.as_deref(), Some("kubectl get pods"));
+        assert_eq!(pods.interval, Some(2.0));
+        assert_eq!(pods.fg.as_deref(), Some("red"));
+        let keybindings = pods.keybindings.expect("keybindings are merged");
+        assert!(keybindings.as_ref().contains_key("a"));
+        assert!(keybindings.as_ref().contains_key("d"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_roundtrip() -> Result<()> {
+        let config: TomlConfig = toml::from_str(indoc! {r#"
+            interval = 1.0
+            field-separator = ","
+            fields = "-1,NAME-,2"
+            [[columns]]
+            align = "right"
+            max-width = 10
+            [keybindings]
+            "ctrl+a" = [ "exit" ]
+        "#})?;
+        let config = config.merge(TomlConfig::default());
+
+        let serialized = toml::to_string(&config)?;
+        let reparsed: TomlConfig = toml::from_str(&serialized)?;
+        assert_eq!(toml::to_string(&reparsed)?, serialized);
+        Ok(())
+    }
+
+    /// Parse the CLI args like clap, but read the `WATCHBIND_*` env variables
+    /// from the given ones instead of the process environment, which is
+    /// shared by all tests.
+    fn parse_with_env(args: &[&str], env: &[(&str, &'static str)]) -> Result<ClapConfig> {
+        let command = ClapConfig::command().mut_args(|arg| {
+            let value = arg
+                .get_env()
+                .and_then(|name| env.iter().find(|(key, _)| name == *key))
+                .map(|&(_, value)| value);
+            // Values of env variables are only used if the arg isn't passed,
+            // just like default values.
+            match value {
+                Some(value) => arg.env(None).default_value(value),
+                None => arg.env(None),
+            }
+        });
+        let matches = command.try_get_matches_from(args)?;
+        Ok(ClapConfig::from_arg_matches(&matches)?)
+    }
+
+    #[test]
+    fn test_global_config_file() {
+        let file = |xdg_config_home: Option<&str>, home: Option<&str>| {
+            global_config_file(xdg_config_home.map(Into::into), home.map(Into::into))
+        };
+        assert_eq!(
+            file(Some("/xdg"), Some("/home/user")),
+            Some(PathBuf::from("/xdg/watchbind/config.toml"))
+        );
+        let fallback = Some(PathBuf::from("/home/user/.config/watchbind/config.toml"));
+        assert_eq!(file(None, Some("/home/user")), fallback);
+        assert_eq!(file(Some(""), Some("/home/user")), fallback);
+        assert_eq!(file(None, None), None);
+    }
+
+    #[test]
+    fn test_no_default_config() -> Result<()> {
+        let dir = write_config_files("no-default-config", &[("config.toml", "interval = 3.0")]);
+        let global_config_file = Some(dir.path().join("config.toml"));
+        let cli = |args: &[&str]| {
+            ClapConfig::try_parse_from(["watchbind"].iter().chain(args).chain(&["ls"]))
+        };
+
+        let config = merge_layers(cli(&[])?, global_config_file.clone(), &mut vec![])?;
+        assert_eq!(config.interval, Some(3.0));
+        let config = merge_layers(
+            cli(&["--no-default-config"])?,
+            global_config_file,
+            &mut vec![],
+        )?;
+        assert_eq!(config.interval, None);
+        // A missing global config file is ignored.
+        let config = merge_layers(
+            cli(&[])?,
+            Some(dir.path().join("missing.toml")),
+            &mut vec![],
+        )?;
+        assert_eq!(config.interval, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_variable_layer() -> Result<()> {
+        let cli = parse_with_env(
+            &["watchbind", "--frozen-columns", "3"],
+            &[
+                ("WATCHBIND_FOOTER_LINES", "2"),
+                ("WATCHBIND_FROZEN_COLUMNS", "1"),
+            ],
+        )?;
+
+        let file_config: TomlConfig = toml::from_str(indoc! {r#"
+            footer-lines = 1
+            frozen-columns = 1
+            header-lines = 1
+        "#})?;
+        let config = TomlConfig::from(cli).merge(file_config);
+        assert_eq!(config.footer_lines, Some(2));
+        assert_eq!(config.frozen_columns, Some(3));
+        assert_eq!(config.header_lines, Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unbind() -> Result<()> {
+        let dir = write_config_files(
+            "unbind",
+            &[
+                (
+                    "base.toml",
+                    indoc! {r#"
+                        [keybindings]
+                        "a" = [ "exit" ]
+                        "b" = [ "exit" ]
+                    "#},
+                ),
+                (
+                    "config.toml",
+                    indoc! {r#"
+                        extends = "base.toml"
+                        unbind = [ "a", "q", "c" ]
+                        [keybindings]
+                        "b" = []
+                        "c" = [ "reload" ]
+                    "#},
+                ),
+            ],
+        );
+
+        let config: Config = TomlConfig::parse(&dir.path().join("config.toml"))?
+            .merge(toml::from_str(r#"watched-command = "ls""#)?)
+            .try_into()?;
+        let keybindings = config.keybindings_parsed.to_string();
+        let keys: Vec<&str> = keybindings
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        assert!(!keys.contains(&"a"));
+        assert!(!keys.contains(&"b"));
+        assert!(keys.contains(&"c"));
+        assert!(!keys.contains(&"q"));
+        assert!(keys.contains(&"r"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_default_keybindings() -> Result<()> {
+        let config: Config = toml::from_str::<TomlConfig>(indoc! {r#"
+            watched-command = "ls"
+            no-default-keybindings = true
+            [keybindings]
+            "a" = [ "exit" ]
+        "#})?
+        .try_into()?;
+        assert_eq!(config.keybindings_parsed.to_string().lines().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_durations() -> Result<()> {
+        for setting in ["interval", "grace-period", "command-timeout"] {
+            for secs in ["-1.0", "nan"] {
+                let toml: TomlConfig =
+                    toml::from_str(&format!("watched-command = \"ls\"\n{setting} = {secs}"))?;
+                assert!(Config::try_from(toml).is_err(), "{setting} = {secs}");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_exec_each_concurrency() -> Result<()> {
+        let config = |concurrency: usize| -> Result<Config> {
+            let toml: TomlConfig = toml::from_str(&format!(
+                "watched-command = \"ls\"\nexec-each-concurrency = {concurrency}"
+            ))?;
+            Config::try_from(toml)
+        };
+        assert_eq!(config(4)?.exec_each_concurrency, 4);
+        assert!(config(0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_subcommands() -> Result<()> {
+        let parse = |args: &[&str]| {
+            let cli = ClapConfig::try_parse_from(["watchbind"].iter().chain(args))?;
+            anyhow::Ok((cli.subcommand, cli.watched_command))
+        };
+        let watched = |command: &[&str]| Some(command.iter().map(|&s| s.to_owned()).collect());
+
+        let (subcommand, _) = parse(&["check", "a.toml", "b.toml"])?;
+        assert!(
+            matches!(subcommand, Some(ClapSubcommand::Check { config_files }) if config_files.len() == 2)
+        );
+        assert!(matches!(parse(&["man"])?.0, Some(ClapSubcommand::Man)));
+
+        // Watched commands with the name of a subcommand are passed after `--`.
+        assert_eq!(parse(&["--", "man", "ls"])?.1, watched(&["man", "ls"]));
+        assert_eq!(parse(&["ls", "man"])?.1, watched(&["ls", "man"]));
+        assert_eq!(parse(&["-i", "2", "check"])?.1, watched(&["check"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_collected_config_files() -> Result<()> {
+        let dir = write_config_files(
+            "collected-config-files",
+            &[
+                ("config.toml", r#"include = [ "keys.toml" ]"#),
+                ("keys.toml", r#"extends = "base/base.toml""#),
+                ("base/base.toml", "interval = 2.0"),
+                ("global.toml", "invalid"),
+            ],
+        );
+        let path = |name: &str| dir.path().join(name);
+        let config_file_arg = path("config.toml").to_string_lossy().into_owned();
+        let cli = || ClapConfig::try_parse_from(["watchbind", "-c", &config_file_arg, "ls"]);
+
+        // A missing global config file is watched until it is created.
+        let mut config_files = vec![];
+        merge_layers(cli()?, Some(path("missing.toml")), &mut config_files)?;
+        let canonical = |name: &str| path(name).canonicalize().expect("file exists");
+        assert_eq!(
+            config_files,
+            vec![
+                path("config.toml"),
+                canonical("keys.toml"),
+                canonical("base/base.toml"),
+                path("missing.toml"),
+            ]
+        );
+
+        // The files are collected even if one of them is invalid.
+        let mut config_files = vec![];
+        assert!(merge_layers(cli()?, Some(path("global.toml")), &mut config_files).is_err());
+        assert_eq!(config_files.last(), Some(&path("global.toml")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_invalid_config() -> Result<()> {
+        let dir = TempDir::new("reload");
+        let config_file = dir.write("config.toml", "interval = 1.0");
+        let config_file_arg = config_file.to_string_lossy().into_owned();
+        let cli = || {
+            ClapConfig::try_parse_from([
+                "watchbind",
+                "--no-default-config",
+                "-c",
+                &config_file_arg,
+                "ls",
+            ])
+        };
+        assert!(Config::from_cli(cli()?).is_ok());
+
+        // The running UI displays the error instead of the invalid config.
+        for invalid in [
+            "interval = -1.0",
+            "grace-period = -1.0",
+            "command-timeout = nan",
+        ] {
+            dir.write("config.toml", invalid);
+            assert!(Config::from_cli(cli()?).is_err(), "{invalid}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_profile() -> Result<()> {
+        let config: TomlConfig = toml::from_str(indoc! {r#"
+            [profile.pods]
+            interval = 2.0
+        "#})?;
+        assert!(config.select_profile("git").is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,123 @@
+use anyhow::{bail, Context, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// The shell that all commands are executed with.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(try_from = "TomlShell", into = "TomlShell")]
+pub enum Shell {
+    /// The shell program and its arguments, which the command is appended
+    /// to, e.g. `["sh", "-c"]`.
+    Program(Vec<String>),
+    /// Commands are split into words, like a POSIX shell would, and executed
+    /// directly, without a shell.
+    None,
+}
+
+impl Shell {
+    /// The program and arguments that execute the command with this shell.
+    pub fn program_and_args(&self, command: &str) -> Result<Vec<String>> {
+        let program_and_args = match self {
+            Self::Program(shell) => {
+                let mut program_and_args = shell.clone();
+                program_and_args.push(command.to_owned());
+                program_and_args
+            }
+            Self::None => shell_words::split(command)
+                .with_context(|| format!("Failed to split command into words: {}", command))?,
+        };
+        if program_and_args.is_empty() {
+            bail!("Cannot execute an empty command");
+        }
+        Ok(program_and_args)
+    }
+}
+
+impl FromStr for Shell {
+    type Err = Error;
+
+    /// Parse either `none`, or the shell program and its arguments, split
+    /// into words like a POSIX shell would, e.g. `bash -o pipefail -c`.
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "none" {
+            return Ok(Self::None);
+        }
+        let shell = shell_words::split(s)
+            .with_context(|| format!("Failed to split shell into words: {}", s))?;
+        shell.try_into()
+    }
+}
+
+impl TryFrom<Vec<String>> for Shell {
+    type Error = Error;
+    fn try_from(shell: Vec<String>) -> Result<Self> {
+        if shell.is_empty() {
+            bail!("The shell must contain at least the shell program, or be \"none\"");
+        }
+        Ok(Self::Program(shell))
+    }
+}
+
+/// The shell is either written as an array of the shell program and its
+/// arguments, or as a string.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum TomlShell {
+    Program(Vec<String>),
+    String(String),
+}
+
+impl TryFrom<TomlShell> for Shell {
+    type Error = Error;
+    fn try_from(toml: TomlShell) -> Result<Self> {
+        match toml {
+            TomlShell::Program(shell) => shell.try_into(),
+            TomlShell::String(s) => s.parse(),
+        }
+    }
+}
+
+impl From<Shell> for TomlShell {
+    fn from(shell: Shell) -> Self {
+        match shell {
+            Shell::Program(shell) => Self::Program(shell),
+            Shell::None => Self::String("none".to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        assert_eq!("none".parse::<Shell>()?, Shell::None);
+        assert_eq!(
+            "bash -o pipefail -c".parse::<Shell>()?,
+            Shell::Program(vec![
+                "bash".to_owned(),
+                "-o".to_owned(),
+                "pipefail".to_owned(),
+                "-c".to_owned()
+            ])
+        );
+        assert!("".parse::<Shell>().is_err());
+        assert!("sh 'unterminated".parse::<Shell>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_program_and_args() -> Result<()> {
+        assert_eq!(
+            "sh -c".parse::<Shell>()?.program_and_args("echo $a | wc")?,
+            vec!["sh", "-c", "echo $a | wc"]
+        );
+        assert_eq!(
+            Shell::None.program_and_args(r#"printf "%s\n" 'a b' c\ d"#)?,
+            vec!["printf", r"%s\n", "a b", "c d"]
+        );
+        assert!(Shell::None.program_and_args("  ").is_err());
+        Ok(())
+    }
+}
@@ -11,6 +11,7 @@ pub struct Styles {
 
 impl Styles {
     // TODO: clippy says too many arguments (and I agree)
+    #[allow(clippy::too_many_arguments)]
     pub fn parse(
         fg: Option<String>,
         bg: Option<String>,
@@ -47,7 +48,7 @@ fn parse_bold(bold: Option<bool>) -> Modifier {
 }
 
 // TODO: create custom Color type and impl from_str and add parser directly into toml and clap structs
-fn parse_color(src: Option<String>) -> Result<Color> {
+pub(super) fn parse_color(src: Option<String>) -> Result<Color> {
     Ok(match src {
         Some(color) => match color.to_lowercase().as_str() {
             "white" => Color::White,
@@ -1,17 +1,27 @@
 mod command;
 mod config;
+#[cfg(test)]
+mod test_utils;
 mod ui;
 
 use std::fs::File;
 
-use crate::config::Config;
+use crate::config::{ClapConfig, Config};
 use anyhow::{Context, Result};
+use clap::Parser;
 use simplelog::{LevelFilter, WriteLogger};
 use ui::UI;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let config = Config::parse()?;
+    let cli = ClapConfig::parse();
+    if let Some(subcommand) = cli.subcommand() {
+        return subcommand.run();
+    }
+    if cli.print_config() {
+        return Config::print(cli);
+    }
+    let config = Config::from_cli(cli.clone())?;
 
     // Setup logging
     if let Some(log_file) = &config.log_file {
@@ -20,5 +30,5 @@ async fn main() -> Result<()> {
         let _ = WriteLogger::init(LevelFilter::Info, simplelog::Config::default(), log_file);
     }
 
-    UI::start(config).await
+    UI::start(config, cli).await
 }
@@ -0,0 +1,101 @@
+use crate::config::{ClapConfig, Config};
+use clap::Parser;
+#[cfg(unix)]
+use nix::{sys::signal::killpg, unistd::Pid};
+#[cfg(unix)]
+use std::time::{Duration, Instant};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A temporary directory for the files of a test, which is removed once it
+/// is dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(test_name: &str) -> Self {
+        // Tests run in parallel, so every directory gets a unique name.
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "watchbind-{}-{}-{}",
+            test_name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).expect("temporary directory can be created");
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Write a file (and its parent directories) into the directory, and
+    /// return its path.
+    pub fn write(&self, name: &str, content: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::create_dir_all(path.parent().expect("file has parent"))
+            .expect("parent directory can be created");
+        fs::write(&path, content).expect("file can be written");
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The command line options of the given args, e.g. `["-b", "a:exit", "ls"]`,
+/// ignoring the global config file.
+pub fn cli(args: &[&str]) -> ClapConfig {
+    let args = ["watchbind", "--no-default-config"].iter().chain(args);
+    ClapConfig::try_parse_from(args).expect("args are valid")
+}
+
+/// The config of the given command line args, ignoring the global config
+/// file.
+pub fn config(args: &[&str]) -> Config {
+    Config::from_cli(cli(args)).expect("config is valid")
+}
+
+/// A command that writes its process group id into the file, and then
+/// sleeps in both the background and the foreground.
+#[cfg(unix)]
+pub fn sleeping_command(pgid_file: &Path) -> String {
+    format!("echo $$ > {}; sleep 100 & sleep 100", pgid_file.display())
+}
+
+/// Wait until the command has written its process group id.
+#[cfg(unix)]
+pub async fn read_pgid(pgid_file: &Path) -> Pid {
+    loop {
+        let pgid = std::fs::read_to_string(pgid_file).unwrap_or_default();
+        if let Ok(pgid) = pgid.trim().parse() {
+            return Pid::from_raw(pgid);
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[cfg(unix)]
+pub fn is_running(pgid: Pid) -> bool {
+    killpg(pgid, None).is_ok()
+}
+
+/// Whether all processes of the group exit within a few seconds. Orphaned
+/// processes only disappear once the init process has reaped them.
+#[cfg(unix)]
+pub async fn has_exited(pgid: Pid) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while is_running(pgid) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    true
+}
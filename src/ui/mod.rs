@@ -2,21 +2,25 @@ mod state;
 mod terminal_manager;
 
 use crate::command::{
-    Blocking, CommandBuilder, ExecutionResult, Interruptible, WasWoken, WithEnv, WithOutput,
+    BackgroundJobs, Blocking, CancelHandle, Cancelled, CommandBuilder, CommandSettings,
+    ExecEachSummary, ExecutionResult, Interruptible, TimedOut, WasWoken, WithEnv, WithOutput,
 };
 use crate::config::KeyEvent;
-use crate::config::{Config, Keybindings};
+use crate::config::{ClapConfig, Config, Keybindings};
 use anyhow::Result;
 use crossterm::event::Event as CrosstermEvent;
 use crossterm::event::EventStream;
 use futures::{future::FutureExt, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use terminal_manager::TerminalManager;
 use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::watch;
 
 pub use state::State;
-pub use state::{EnvVariable, EnvVariables};
+pub use state::{confirmation_answer, ChooserAction, EnvVariable, EnvVariables, ErrorSource};
 
 pub type WatchedCommand = CommandBuilder<Blocking, WithEnv, WithOutput, Interruptible>;
 
@@ -24,16 +28,28 @@ pub struct UI {
     blocking_state: BlockingState,
     terminal_manager: TerminalManager,
     state: State,
-    watch_rate: Duration,
     keybindings: Arc<Keybindings>,
+    /// The key that cancels the current blocking operation.
+    cancel_key: KeyEvent,
     remaining_operations: Option<RemainingOperations>,
+    /// The keys pressed while blocking, which are handled once unblocked.
+    queued_keys: KeyQueue,
+    /// The background commands, which are terminated on exit.
+    background_jobs: BackgroundJobs,
+    /// How long to wait for commands to terminate, before killing them.
+    grace_period: Duration,
+    /// The command line options the config was parsed from, which are
+    /// parsed again when reloading the config.
+    cli: ClapConfig,
     channels: Channels,
 }
 
 /// After having blocked, there might be some remaining operations, that
 /// were originally requested, which we still have to execute.
-#[derive(Debug)]
 struct RemainingOperations {
+    /// The keybindings that were active when the operations were requested,
+    /// which might have been replaced by reloading the config since.
+    keybindings: Arc<Keybindings>,
     /// The key that is mapped to the remaining operations. Saving this is
     /// more (memory) efficient than copying the an partial Operations type.
     key: KeyEvent,
@@ -41,6 +57,48 @@ struct RemainingOperations {
     remaining_index: usize,
 }
 
+/// The keys pressed while blocking, if queueing is enabled, which are
+/// handled in the order they were pressed once unblocked.
+struct KeyQueue {
+    keys: VecDeque<KeyEvent>,
+    /// Whether keys are queued, instead of discarded.
+    enabled: bool,
+    /// Limits how many keys are replayed after a long blocking operation.
+    max_keys: usize,
+}
+
+impl KeyQueue {
+    fn new(enabled: bool, max_keys: usize) -> Self {
+        Self {
+            keys: VecDeque::new(),
+            enabled,
+            max_keys,
+        }
+    }
+
+    /// Queue a key, unless queueing is disabled, the queue is full, or the
+    /// key is the cancel key or bound to operations that mustn't be queued.
+    fn push(&mut self, key: KeyEvent, keybindings: &Keybindings, cancel_key: &KeyEvent) {
+        if !self.enabled || key == *cancel_key || self.keys.len() >= self.max_keys {
+            return;
+        }
+        let queueable = keybindings
+            .get_operations(&key)
+            .is_some_and(|ops| ops.are_queueable());
+        if queueable {
+            self.keys.push_back(key);
+        }
+    }
+
+    fn pop(&mut self) -> Option<KeyEvent> {
+        self.keys.pop_front()
+    }
+
+    fn clear(&mut self) {
+        self.keys.clear();
+    }
+}
+
 /// All mpsc channels we save in the UI.
 struct Channels {
     event_tx: Sender<Event>,
@@ -48,6 +106,17 @@ struct Channels {
     /// We don't store the receivers for the reload and subcommand channels,
     /// because their ownership is passed to the polling tasks.
     reload_tx: Sender<InterruptSignal>,
+    /// Updates the watch rate of the watched command polling task.
+    watch_rate_tx: watch::Sender<Duration>,
+    /// Updates the keybindings of the terminal event polling task.
+    keybindings_tx: watch::Sender<Arc<Keybindings>>,
+    /// Updates the cancel key of the terminal event polling task.
+    cancel_key_tx: watch::Sender<KeyEvent>,
+    /// Makes the terminal event polling task send all keys, instead of only
+    /// the bound ones, e.g. to answer a confirmation.
+    capture_keys_tx: watch::Sender<bool>,
+    /// Updates the config files watched by the config file polling task.
+    config_files_tx: watch::Sender<Vec<PathBuf>>,
 }
 
 /// Contains all the state that we cannot save in UI directly, because by being
@@ -55,6 +124,11 @@ struct Channels {
 /// preventing us from calling methods on it.
 struct PollingState {
     watched_command: WatchedCommand,
+    watch_rate_rx: watch::Receiver<Duration>,
+    keybindings_rx: watch::Receiver<Arc<Keybindings>>,
+    cancel_key_rx: watch::Receiver<KeyEvent>,
+    capture_keys_rx: watch::Receiver<bool>,
+    config_files_rx: watch::Receiver<Vec<PathBuf>>,
 }
 
 /// Events that are handled in our main UI/IO loop.
@@ -62,8 +136,14 @@ pub enum Event {
     CommandOutput(Result<String>),
     KeyPressed(KeyEvent),
     TerminalResized,
+    ConfigFileChanged,
     SubcommandCompleted(Result<()>),
     SubcommandForEnvCompleted(Result<EnvVariables>),
+    /// The subcommand of a `choose` operation has completed, and its output
+    /// lines are the options for the env variable.
+    SubcommandForChoiceCompleted(EnvVariable, Result<String>),
+    /// All executions of an `exec-each` operation have completed.
+    ExecEachCompleted(ExecEachSummary),
 }
 
 // TODO: maybe move to operations module
@@ -74,11 +154,20 @@ pub enum RequestedAction {
     /// Reload/rerun the main command, while blocking.
     ReloadWatchedCommand,
     /// Signals that a blocking subcommand has started executing, so we
-    /// should block.
-    ExecutingBlockingSubcommand,
+    /// should block until it completes or is cancelled.
+    ExecutingBlockingSubcommand(CancelHandle),
     /// Signals that a blocking subcommand used to set env variables has
-    /// started executing, so we should block.
-    ExecutingBlockingSubcommandForEnv,
+    /// started executing, so we should block until it completes or is
+    /// cancelled.
+    ExecutingBlockingSubcommandForEnv(CancelHandle),
+    /// Signals that a blocking subcommand, whose output lines are the options
+    /// to choose an env variable's value from, has started executing, so we
+    /// should block until it completes or is cancelled, and then until an
+    /// option is chosen.
+    ExecutingBlockingSubcommandForChoice(CancelHandle),
+    /// Signals that a confirmation is displayed, so we should block until
+    /// it is answered.
+    Confirming,
     /// Exit the application.
     Exit,
 }
@@ -91,8 +180,13 @@ enum BlockingState {
     #[default]
     Unblocked,
     BlockedReloadingWatchedCommand,
-    BlockedExecutingSubcommand,
-    BlockedExecutingSubcommandForEnv,
+    /// Contains the handle to cancel the subcommand, unless it has already
+    /// been cancelled.
+    BlockedExecutingSubcommand(Option<CancelHandle>),
+    BlockedExecutingSubcommandForEnv(Option<CancelHandle>),
+    BlockedExecutingSubcommandForChoice(Option<CancelHandle>),
+    BlockedChoosing,
+    BlockedConfirming,
 }
 
 /// Draws the UI. Prevents code duplication, because making this a method would
@@ -109,9 +203,10 @@ macro_rules! draw {
 /// Save all remaining operations, if there are any. Used as macro to prevent
 /// borrow-checking problems.
 macro_rules! save_remaining_operations {
-    ($self:expr, $key:expr, $remaining_index:expr, $operations:expr) => {
+    ($self:expr, $keybindings:expr, $key:expr, $remaining_index:expr, $operations:expr) => {
         if $remaining_index < $operations.len() {
             $self.remaining_operations = Some(RemainingOperations {
+                keybindings: $keybindings.clone(),
                 key: $key,
                 remaining_index: $remaining_index,
             });
@@ -127,23 +222,32 @@ enum ControlFlow {
 
 impl UI {
     /// Initiates the user interface.
-    pub async fn start(config: Config) -> Result<()> {
-        let (ui, polling_state) = UI::new(config).await?;
+    pub async fn start(config: Config, cli: ClapConfig) -> Result<()> {
+        let (ui, polling_state) = UI::new(config, cli, TerminalManager::new()?).await?;
         ui.run(polling_state).await?;
         Ok(())
     }
 
-    async fn new(config: Config) -> Result<(Self, PollingState)> {
-        let terminal_manager = TerminalManager::new()?;
-
-        let env_variables = EnvVariables::generate_initial(config.initial_env_variables).await?;
+    async fn new(
+        config: Config,
+        cli: ClapConfig,
+        terminal_manager: TerminalManager,
+    ) -> Result<(Self, PollingState)> {
+        let command_settings = config.command_settings();
+        let env_variables =
+            EnvVariables::generate_initial(config.initial_env_variables, &command_settings).await?;
         let keybindings_str = config.keybindings_parsed.to_string();
         let state = State::new(
             config.header_lines,
+            config.footer_lines,
             config.fields,
+            config.columns,
+            config.frozen_columns,
+            config.wrap_lines,
             config.styles,
             keybindings_str,
             env_variables,
+            command_settings.working_dir.clone(),
         );
 
         /// The event buffer capacity is restricted to 100 (seems to be a
@@ -160,47 +264,112 @@ impl UI {
         let (reload_tx, reload_rx) = mpsc::channel(POLLING_TASKS_BUFFER_CAPACITY);
 
         let env_variables = state.get_env();
-        let keybindings = Keybindings::from_parsed(config.keybindings_parsed, &env_variables);
+        let keybindings = Arc::new(Keybindings::from_parsed(
+            config.keybindings_parsed,
+            &env_variables,
+            &command_settings,
+        ));
+
+        let (watch_rate_tx, watch_rate_rx) = watch::channel(config.watch_rate);
+        let (keybindings_tx, keybindings_rx) = watch::channel(keybindings.clone());
+        let (cancel_key_tx, cancel_key_rx) = watch::channel(config.cancel_key.clone());
+        let (capture_keys_tx, capture_keys_rx) = watch::channel(false);
+        let (config_files_tx, config_files_rx) = watch::channel(config.config_files);
 
         let polling_state = PollingState {
-            watched_command: CommandBuilder::new(config.watched_command)
+            watched_command: CommandBuilder::new(config.watched_command, command_settings)
                 .blocking()
                 .with_output()
                 .interruptible(reload_rx)
                 .with_env(env_variables.clone()),
+            watch_rate_rx,
+            keybindings_rx,
+            cancel_key_rx,
+            capture_keys_rx,
+            config_files_rx,
         };
 
         let ui = Self {
             blocking_state: BlockingState::default(),
             terminal_manager,
             state,
-            watch_rate: config.watch_rate,
-            keybindings: Arc::new(keybindings),
+            keybindings,
+            cancel_key: config.cancel_key,
             remaining_operations: None,
+            queued_keys: KeyQueue::new(config.queue_keys, config.max_queued_keys),
+            background_jobs: BackgroundJobs::default(),
+            grace_period: config.grace_period,
+            cli,
             channels: Channels {
                 event_tx,
                 event_rx,
                 reload_tx,
+                watch_rate_tx,
+                keybindings_tx,
+                cancel_key_tx,
+                capture_keys_tx,
+                config_files_tx,
             },
         };
 
         Ok((ui, polling_state))
     }
 
-    /// Run the main event loop indefinitely until an Exit request is received.
+    /// Run the main event loop indefinitely until an Exit request is received,
+    /// and terminate all commands that are still running afterwards.
     async fn run(mut self, polling_state: PollingState) -> Result<()> {
         // Launch polling tasks
-        tokio::spawn(poll_execute_watched_command(
+        let watched_command_task = tokio::spawn(poll_execute_watched_command(
             polling_state.watched_command,
-            self.watch_rate,
+            polling_state.watch_rate_rx,
             self.channels.event_tx.clone(),
         ));
         tokio::spawn(poll_terminal_events(
-            self.keybindings.clone(),
+            polling_state.keybindings_rx,
+            polling_state.cancel_key_rx,
+            polling_state.capture_keys_rx,
             self.channels.event_tx.clone(),
         ));
+        tokio::spawn(poll_config_files(
+            polling_state.config_files_rx,
+            self.channels.event_tx.clone(),
+        ));
+
+        let result = self.event_loop().await;
+
+        let Self {
+            terminal_manager,
+            background_jobs,
+            grace_period,
+            channels,
+            ..
+        } = self;
+        // Restore the terminal right away, instead of after all commands
+        // have terminated.
+        drop(terminal_manager);
+        // Closing the channels terminates the watched command, and stops its
+        // polling task.
+        drop(channels);
+        let _ = watched_command_task.await;
+        background_jobs.terminate_all(grace_period).await?;
+
+        result
+    }
 
+    /// Handle events until an Exit request is received.
+    async fn event_loop(&mut self) -> Result<()> {
         'event_loop: loop {
+            // Handle the keys queued while blocking before any new events,
+            // as they were pressed earlier.
+            if let BlockingState::Unblocked = self.blocking_state {
+                if let Some(key) = self.queued_keys.pop() {
+                    if let ControlFlow::Exit = self.handle_key_event(key).await? {
+                        break 'event_loop;
+                    }
+                    continue 'event_loop;
+                }
+            }
+
             draw!(self)?;
 
             let Some(event) = self.channels.event_rx.recv().await else {
@@ -208,9 +377,27 @@ impl UI {
             };
 
             // Handle events that are handled the same in every state.
-            if let Event::TerminalResized = &event {
-                // Reload the UI
-                continue 'event_loop;
+            match &event {
+                Event::TerminalResized => {
+                    // Reload the UI
+                    continue 'event_loop;
+                }
+                Event::ConfigFileChanged => {
+                    self.reload_config();
+                    continue 'event_loop;
+                }
+                Event::ExecEachCompleted(summary) => {
+                    self.show_exec_each_summary(summary);
+                    continue 'event_loop;
+                }
+                Event::KeyPressed(key)
+                    if *key == self.cancel_key
+                        && !matches!(self.blocking_state, BlockingState::Unblocked) =>
+                {
+                    self.cancel_blocking().await;
+                    continue 'event_loop;
+                }
+                _ => {}
             }
             // Note: all states also handle Event::CommandOutput very similarly,
             // but taking lines out of event here leaves event in a partially
@@ -218,52 +405,123 @@ impl UI {
             // the code duplication below for now.
 
             match self.blocking_state {
-                BlockingState::BlockedReloadingWatchedCommand => {
-                    if let Event::CommandOutput(lines) = event {
-                        self.state.update_lines(lines?)?;
+                BlockingState::BlockedReloadingWatchedCommand => match event {
+                    Event::CommandOutput(lines) => {
+                        self.update_lines(lines)?;
 
                         if let ControlFlow::Exit = self.conclude_blocking().await? {
                             break 'event_loop;
                         }
                     }
+                    Event::KeyPressed(key) => self.queue_key(key),
+                    _ => {}
+                },
+                BlockingState::BlockedExecutingSubcommand(_) => {
+                    match event {
+                        Event::CommandOutput(lines) => {
+                            // We handle new output lines, but don't exit the
+                            // blocking state.
+                            self.update_lines(lines)?;
+                        }
+                        Event::KeyPressed(key) => self.queue_key(key),
+                        Event::SubcommandCompleted(result) => {
+                            let completed = self.handle_subcommand_result(result)?;
+                            if completed.is_some() {
+                                if let ControlFlow::Exit = self.conclude_blocking().await? {
+                                    break 'event_loop;
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
                 }
-                BlockingState::BlockedExecutingSubcommand => {
+                BlockingState::BlockedExecutingSubcommandForEnv(_) => {
                     match event {
                         Event::CommandOutput(lines) => {
                             // We handle new output lines, but don't exit the
                             // blocking state.
-                            self.state.update_lines(lines?)?;
+                            self.update_lines(lines)?;
                         }
-                        Event::SubcommandCompleted(potential_error) => {
-                            potential_error?;
+                        Event::KeyPressed(key) => self.queue_key(key),
+                        Event::SubcommandForEnvCompleted(result) => {
+                            if let Some(new_env_variables) =
+                                self.handle_subcommand_result(result)?
+                            {
+                                self.state.set_env(new_env_variables).await;
 
-                            if let ControlFlow::Exit = self.conclude_blocking().await? {
-                                break 'event_loop;
+                                if let ControlFlow::Exit = self.conclude_blocking().await? {
+                                    break 'event_loop;
+                                }
                             }
                         }
                         _ => {}
                     }
                 }
-                BlockingState::BlockedExecutingSubcommandForEnv => {
+                BlockingState::BlockedExecutingSubcommandForChoice(_) => {
                     match event {
                         Event::CommandOutput(lines) => {
                             // We handle new output lines, but don't exit the
                             // blocking state.
-                            self.state.update_lines(lines?)?;
+                            self.update_lines(lines)?;
                         }
-                        Event::SubcommandForEnvCompleted(new_env_variables) => {
-                            self.state.set_env(new_env_variables?).await;
+                        Event::KeyPressed(key) => self.queue_key(key),
+                        Event::SubcommandForChoiceCompleted(env_variable, result) => {
+                            if let Some(options) = self.handle_subcommand_result(result)? {
+                                self.state.show_chooser(env_variable, options)?;
+                                self.blocking_state = BlockingState::BlockedChoosing;
+                                // The keys used to choose are usually not
+                                // bound.
+                                let _ = self.channels.capture_keys_tx.send(true);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                BlockingState::BlockedChoosing => match event {
+                    Event::CommandOutput(lines) => {
+                        self.update_lines(lines)?;
+                    }
+                    Event::KeyPressed(key) => match self.state.handle_chooser_key(&key)? {
+                        ChooserAction::Continue => {}
+                        ChooserAction::Choose(env_variable, value) => {
+                            self.close_chooser();
+                            self.state
+                                .set_env([(env_variable, value)].into_iter().collect())
+                                .await;
 
                             if let ControlFlow::Exit = self.conclude_blocking().await? {
                                 break 'event_loop;
                             }
                         }
-                        _ => {}
+                        ChooserAction::Abort => {
+                            self.close_chooser();
+                            self.abort_blocking();
+                        }
+                    },
+                    _ => {}
+                },
+                BlockingState::BlockedConfirming => match event {
+                    Event::CommandOutput(lines) => {
+                        self.update_lines(lines)?;
                     }
-                }
+                    Event::KeyPressed(key) => match confirmation_answer(&key) {
+                        Some(true) => {
+                            self.close_confirmation();
+                            if let ControlFlow::Exit = self.conclude_blocking().await? {
+                                break 'event_loop;
+                            }
+                        }
+                        Some(false) => {
+                            self.close_confirmation();
+                            self.abort_blocking();
+                        }
+                        None => {}
+                    },
+                    _ => {}
+                },
                 BlockingState::Unblocked => match event {
                     Event::CommandOutput(lines) => {
-                        self.state.update_lines(lines?)?;
+                        self.update_lines(lines)?;
                     }
                     Event::KeyPressed(key) => {
                         if let ControlFlow::Exit = self.handle_key_event(key).await? {
@@ -283,34 +541,61 @@ impl UI {
     /// operations, we update the remaining operations.
     async fn handle_key_event_given_starting_index(
         &mut self,
+        keybindings: Arc<Keybindings>,
         key: KeyEvent,
         starting_index: usize,
     ) -> Result<ControlFlow> {
-        if let Some(ops) = self.keybindings.get_operations(&key) {
+        if let Some(ops) = keybindings.get_operations(&key) {
             for (idx, op) in ops.into_iter().enumerate().skip(starting_index) {
-                match op.execute(&mut self.state, &self.channels.event_tx).await? {
+                match op
+                    .execute(
+                        &mut self.state,
+                        &self.channels.event_tx,
+                        &mut self.background_jobs,
+                    )
+                    .await?
+                {
                     RequestedAction::Exit => return Ok(ControlFlow::Exit),
                     RequestedAction::ReloadWatchedCommand => {
                         // Send the command execution an interrupt signal
                         // causing the execution to be reloaded.
-                        if self.channels.reload_tx.send(InterruptSignal).await.is_err() {
+                        let reload = self.channels.reload_tx.send(InterruptSignal::Reload);
+                        if reload.await.is_err() {
                             return Ok(ControlFlow::Exit);
                         }
 
-                        save_remaining_operations!(self, key, idx + 1, ops);
+                        save_remaining_operations!(self, keybindings, key, idx + 1, ops);
                         self.blocking_state = BlockingState::BlockedReloadingWatchedCommand;
 
                         return Ok(ControlFlow::Continue);
                     }
-                    RequestedAction::ExecutingBlockingSubcommand => {
-                        save_remaining_operations!(self, key, idx + 1, ops);
-                        self.blocking_state = BlockingState::BlockedExecutingSubcommand;
+                    RequestedAction::ExecutingBlockingSubcommand(cancel_handle) => {
+                        save_remaining_operations!(self, keybindings, key, idx + 1, ops);
+                        self.blocking_state =
+                            BlockingState::BlockedExecutingSubcommand(Some(cancel_handle));
+
+                        return Ok(ControlFlow::Continue);
+                    }
+                    RequestedAction::ExecutingBlockingSubcommandForEnv(cancel_handle) => {
+                        save_remaining_operations!(self, keybindings, key, idx + 1, ops);
+                        self.blocking_state =
+                            BlockingState::BlockedExecutingSubcommandForEnv(Some(cancel_handle));
 
                         return Ok(ControlFlow::Continue);
                     }
-                    RequestedAction::ExecutingBlockingSubcommandForEnv => {
-                        save_remaining_operations!(self, key, idx + 1, ops);
-                        self.blocking_state = BlockingState::BlockedExecutingSubcommandForEnv;
+                    RequestedAction::ExecutingBlockingSubcommandForChoice(cancel_handle) => {
+                        save_remaining_operations!(self, keybindings, key, idx + 1, ops);
+                        self.blocking_state =
+                            BlockingState::BlockedExecutingSubcommandForChoice(Some(cancel_handle));
+
+                        return Ok(ControlFlow::Continue);
+                    }
+                    RequestedAction::Confirming => {
+                        save_remaining_operations!(self, keybindings, key, idx + 1, ops);
+                        self.blocking_state = BlockingState::BlockedConfirming;
+                        // The keys answering the confirmation are usually
+                        // not bound.
+                        let _ = self.channels.capture_keys_tx.send(true);
 
                         return Ok(ControlFlow::Continue);
                     }
@@ -331,17 +616,16 @@ impl UI {
     /// that we transition to the unblocked state, because we might still have
     /// to execute remaining blocking operations.
     async fn conclude_blocking(&mut self) -> Result<ControlFlow> {
-        // Since we are coming from a blocking state, we need to delete all
-        // events we received while we were blocking.
-        clear_buffer(&mut self.channels.event_rx);
+        self.drain_blocked_events();
 
         match self.remaining_operations.take() {
             Some(RemainingOperations {
+                keybindings,
                 key,
                 remaining_index,
             }) => {
                 // Execute any remaining operations.
-                self.handle_key_event_given_starting_index(key, remaining_index)
+                self.handle_key_event_given_starting_index(keybindings, key, remaining_index)
                     .await
             }
             None => {
@@ -352,41 +636,243 @@ impl UI {
         }
     }
 
+    /// Cancel the current blocking operation. A running subcommand is
+    /// terminated first, and only its completion aborts the blocking state,
+    /// so that it can't be mistaken for the completion of a later subcommand.
+    /// The reloaded watched command is terminated, and the lines of its
+    /// previous execution are kept.
+    async fn cancel_blocking(&mut self) {
+        match &mut self.blocking_state {
+            BlockingState::Unblocked => {}
+            BlockingState::BlockedReloadingWatchedCommand => {
+                // The polling task only stops once the main thread has
+                // exited, so sending can't fail while we are running.
+                let _ = self.channels.reload_tx.send(InterruptSignal::Cancel).await;
+                self.abort_blocking();
+            }
+            BlockingState::BlockedChoosing => {
+                self.close_chooser();
+                self.abort_blocking();
+            }
+            BlockingState::BlockedConfirming => {
+                self.close_confirmation();
+                self.abort_blocking();
+            }
+            BlockingState::BlockedExecutingSubcommand(cancel_handle)
+            | BlockingState::BlockedExecutingSubcommandForEnv(cancel_handle)
+            | BlockingState::BlockedExecutingSubcommandForChoice(cancel_handle) => {
+                if let Some(cancel_handle) = cancel_handle.take() {
+                    cancel_handle.cancel();
+                }
+            }
+        }
+    }
+
+    /// Hide the confirmation, and stop capturing the keys used to answer it.
+    fn close_confirmation(&mut self) {
+        self.state.hide_confirmation();
+        let _ = self.channels.capture_keys_tx.send(false);
+    }
+
+    /// Hide the chooser, and stop capturing the keys used to choose.
+    fn close_chooser(&mut self) {
+        self.state.hide_chooser();
+        let _ = self.channels.capture_keys_tx.send(false);
+    }
+
+    /// Abort the current blocking state, without executing any remaining
+    /// operations or queued keys.
+    fn abort_blocking(&mut self) {
+        self.drain_blocked_events();
+        self.queued_keys.clear();
+        self.remaining_operations = None;
+        self.blocking_state = BlockingState::Unblocked;
+    }
+
+    /// Since we are coming from a blocking state, we need to delete all
+    /// events we received while we were blocking, except for config file
+    /// changes and `exec-each` summaries, which would otherwise be lost, and
+    /// key presses, which are queued if enabled.
+    fn drain_blocked_events(&mut self) {
+        let mut config_file_changed = false;
+        while let Ok(event) = self.channels.event_rx.try_recv() {
+            match event {
+                Event::ConfigFileChanged => config_file_changed = true,
+                Event::ExecEachCompleted(summary) => self.show_exec_each_summary(&summary),
+                Event::KeyPressed(key) => self.queue_key(key),
+                _ => {}
+            }
+        }
+        if config_file_changed {
+            self.reload_config();
+        }
+    }
+
+    /// Queue a key pressed while blocking, if queueing is enabled.
+    fn queue_key(&mut self, key: KeyEvent) {
+        self.queued_keys
+            .push(key, &self.keybindings, &self.cancel_key);
+    }
+
+    /// Display the summary of an `exec-each` operation, which is an error if
+    /// any of its executions failed.
+    fn show_exec_each_summary(&mut self, summary: &ExecEachSummary) {
+        let message = format!("exec-each: {}", summary);
+        if summary.has_failures() {
+            self.state.show_error(ErrorSource::Subcommand, message);
+        } else {
+            self.state.show_info(ErrorSource::Subcommand, message);
+        }
+    }
+
+    /// Display the output lines of the watched command. If it timed out, the
+    /// error is displayed instead of exiting.
+    fn update_lines(&mut self, lines: Result<String>) -> Result<()> {
+        match lines {
+            Ok(lines) => {
+                self.state.hide_error(ErrorSource::WatchedCommand);
+                self.state.update_lines(lines)
+            }
+            Err(e) if e.is::<TimedOut>() => {
+                self.state
+                    .show_error(ErrorSource::WatchedCommand, format!("{:#}", e));
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// If a blocking subcommand timed out or was cancelled, the remaining
+    /// operations are aborted (and a timeout is displayed as an error),
+    /// instead of exiting.
+    fn handle_subcommand_result<T>(&mut self, result: Result<T>) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.is::<Cancelled>() => {
+                self.abort_blocking();
+                Ok(None)
+            }
+            Err(e) if e.is::<TimedOut>() => {
+                self.state
+                    .show_error(ErrorSource::Subcommand, format!("{:#}", e));
+                self.abort_blocking();
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     /// Execute the operations associated with a key event.
     async fn handle_key_event(&mut self, key: KeyEvent) -> Result<ControlFlow> {
-        self.handle_key_event_given_starting_index(key, 0).await
+        self.state.hide_error(ErrorSource::Subcommand);
+        self.handle_key_event_given_starting_index(self.keybindings.clone(), key, 0)
+            .await
+    }
+
+    /// Re-parse the config, and apply the new keybindings, styles, fields and
+    /// watch rate, while keeping the current lines, cursor, selection and env
+    /// variables. If the config is invalid, the error is displayed instead.
+    fn reload_config(&mut self) {
+        let mut config_files = vec![];
+        let config = match Config::from_cli_collecting_files(self.cli.clone(), &mut config_files) {
+            Ok(config) => config,
+            Err(e) => {
+                // Keep watching the previous files as well, since the files
+                // after the invalid one haven't been read.
+                self.channels.config_files_tx.send_modify(|previous| {
+                    for path in config_files {
+                        if !previous.contains(&path) {
+                            previous.push(path);
+                        }
+                    }
+                });
+                self.state.show_error(
+                    ErrorSource::ConfigFile,
+                    format!("Failed to reload config file:\n{:#}", e),
+                );
+                return;
+            }
+        };
+        self.state.hide_error(ErrorSource::ConfigFile);
+        self.grace_period = config.grace_period;
+        self.queued_keys.enabled = config.queue_keys;
+        self.queued_keys.max_keys = config.max_queued_keys;
+
+        let keybindings_str = config.keybindings_parsed.to_string();
+        let command_settings = CommandSettings {
+            // Keep the working directory, which might have been changed.
+            working_dir: self.state.get_working_dir(),
+            ..config.command_settings()
+        };
+        self.keybindings = Arc::new(Keybindings::from_parsed(
+            config.keybindings_parsed,
+            &self.state.get_env(),
+            &command_settings,
+        ));
+        if let Err(e) = self.state.update_config(
+            config.header_lines,
+            config.footer_lines,
+            config.fields,
+            config.columns,
+            config.frozen_columns,
+            config.wrap_lines,
+            config.styles,
+            keybindings_str,
+        ) {
+            self.state.show_error(
+                ErrorSource::ConfigFile,
+                format!("Failed to reload config file:\n{:#}", e),
+            );
+        }
+
+        // The polling tasks only stop once the main thread has exited, so
+        // sending can't fail while we are running.
+        let _ = self.channels.keybindings_tx.send(self.keybindings.clone());
+        let _ = self.channels.watch_rate_tx.send(config.watch_rate);
+        let _ = self.channels.cancel_key_tx.send(config.cancel_key.clone());
+        let _ = self.channels.config_files_tx.send(config.config_files);
+        self.cancel_key = config.cancel_key;
     }
 }
 
 /// The interrupt signal that is sent to the command polling thread when the
-/// command execution should be reloaded.
-pub struct InterruptSignal;
+/// command execution should be reloaded, or cancelled.
+pub enum InterruptSignal {
+    Reload,
+    Cancel,
+}
 
 /// Continuously executes the command in a loop, separated by sleeps of
 /// watch_rate duration. Additionally, can be signalled to reload the execution
-/// of the command, which simply wakes up this thread sooner.
+/// of the command, which simply wakes up this thread sooner, or to cancel the
+/// current execution, which then waits for the next one.
 /// The stdout of successful executions is sent back to the main thread.
 async fn poll_execute_watched_command(
     mut watched_command: WatchedCommand,
-    watch_rate: Duration,
+    watch_rate_rx: watch::Receiver<Duration>,
     event_tx: Sender<Event>,
 ) {
     loop {
         let start_time = Instant::now();
+        let watch_rate = *watch_rate_rx.borrow();
 
         let output_lines_result = match watched_command.execute().await {
             Ok(ExecutionResult::Interrupted) => continue,
-            Ok(ExecutionResult::Stdout(output_lines)) => Ok(output_lines),
-            Err(e) => Err(e),
+            Ok(ExecutionResult::Cancelled) => None,
+            Ok(ExecutionResult::ChannelClosed) => break,
+            Ok(ExecutionResult::Stdout(output_lines)) => Some(Ok(output_lines)),
+            Err(e) => Some(Err(e)),
         };
 
-        if event_tx
-            .send(Event::CommandOutput(output_lines_result))
-            .await
-            .is_err()
-        {
-            break;
-        };
+        if let Some(output_lines_result) = output_lines_result {
+            if event_tx
+                .send(Event::CommandOutput(output_lines_result))
+                .await
+                .is_err()
+            {
+                break;
+            };
+        }
 
         // If all senders (i.e. the main thread) have been dropped, we abort.
         if watch_rate == Duration::ZERO {
@@ -412,9 +898,15 @@ async fn poll_execute_watched_command(
 
 /// Continuously listens for terminal-related events, and sends relevant events
 /// back to the main thread.
-/// For key events, only those that are part of a keybinding are sent.
+/// For key events, only those that are part of a keybinding, or the cancel
+/// key, are sent, unless all keys are captured.
 /// For terminal resizing, we always notify.
-async fn poll_terminal_events(keybindings: Arc<Keybindings>, event_tx: Sender<Event>) {
+async fn poll_terminal_events(
+    keybindings_rx: watch::Receiver<Arc<Keybindings>>,
+    cancel_key_rx: watch::Receiver<KeyEvent>,
+    capture_keys_rx: watch::Receiver<bool>,
+    event_tx: Sender<Event>,
+) {
     // TODO: don't listen for events when blocked, isn't displayed anyways
     let mut reader = EventStream::new();
 
@@ -424,7 +916,9 @@ async fn poll_terminal_events(keybindings: Arc<Keybindings>, event_tx: Sender<Ev
         match event.await {
             Some(Ok(CrosstermEvent::Key(key_event))) => {
                 if let Ok(key) = key_event.try_into() {
-                    if keybindings.get_operations(&key).is_some() {
+                    let is_bound = keybindings_rx.borrow().get_operations(&key).is_some();
+                    let is_captured = *capture_keys_rx.borrow();
+                    if is_bound || is_captured || key == *cancel_key_rx.borrow() {
                         // Ideally, we would send the &Operations directly, instead
                         // of only sending the key event, which the main thread
                         // then as to look-up again in the Keybindings hashmap,
@@ -437,9 +931,10 @@ async fn poll_terminal_events(keybindings: Arc<Keybindings>, event_tx: Sender<Ev
                 }
             }
             Some(Ok(CrosstermEvent::Resize(_, _))) => {
-                if event_tx.send(Event::TerminalResized).await.is_err() {
+                let sent = event_tx.send(Event::TerminalResized).await;
+                if sent.is_err() {
                     break;
-                };
+                }
             }
             _ => {}
         }
@@ -448,8 +943,217 @@ async fn poll_terminal_events(keybindings: Arc<Keybindings>, event_tx: Sender<Ev
     log::info!("Shutting down event listener task");
 }
 
-/// Remove all elements from the receiving channel buffer, until is is either
-/// empty or was closed by the sender(s).
-fn clear_buffer<T>(rx: &mut Receiver<T>) {
-    while rx.try_recv().is_ok() {}
+/// Periodically checks whether any of the config files has been modified, and
+/// notifies the main thread if so. Files that are newly watched are only
+/// compared from then on.
+async fn poll_config_files(
+    config_files_rx: watch::Receiver<Vec<PathBuf>>,
+    event_tx: Sender<Event>,
+) {
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    async fn modified(config_file: &Path) -> Option<SystemTime> {
+        tokio::fs::metadata(config_file)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    let mut last_modified = HashMap::new();
+    // If the sender (i.e. the main thread) has been dropped, we abort.
+    while config_files_rx.has_changed().is_ok() {
+        let config_files = config_files_rx.borrow().clone();
+        let mut changed = false;
+        let mut current_modified = HashMap::new();
+        for config_file in config_files {
+            let modified = modified(&config_file).await;
+            if let Some(last_modified) = last_modified.get(&config_file) {
+                changed |= *last_modified != modified;
+            }
+            current_modified.insert(config_file, modified);
+        }
+        last_modified = current_modified;
+
+        if changed && event_tx.send(Event::ConfigFileChanged).await.is_err() {
+            break;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    log::info!("Shutting down config file watcher task");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, TempDir};
+    use std::{fs, time::SystemTime};
+    use tokio::sync::{mpsc, Mutex};
+
+    #[test]
+    fn test_key_queue() -> Result<()> {
+        let config = test_utils::config(&[
+            "--no-default-keybindings",
+            "-b",
+            "a:cursor down 1,b:cursor up 1,d:exec -- rm $line+no-queue",
+            "ls",
+        ]);
+        let command_settings = config.command_settings();
+        let keybindings = Keybindings::from_parsed(
+            config.keybindings_parsed,
+            &Arc::new(Mutex::new(EnvVariables::default())),
+            &command_settings,
+        );
+        let cancel_key: KeyEvent = "ctrl+c".parse()?;
+        let keys = |keys: &[&str]| -> Result<Vec<KeyEvent>> {
+            keys.iter().map(|key| key.parse()).collect()
+        };
+
+        // Unbound keys, keys with `no-queue` and the cancel key aren't queued.
+        let mut queue = KeyQueue::new(true, 3);
+        for key in keys(&["b", "x", "d", "ctrl+c", "a"])? {
+            queue.push(key, &keybindings, &cancel_key);
+        }
+        assert_eq!(queue.keys, keys(&["b", "a"])?);
+
+        // Keys are replayed in the order they were pressed, up to the limit.
+        for key in keys(&["b", "a", "b"])? {
+            queue.push(key, &keybindings, &cancel_key);
+        }
+        let replayed: Vec<KeyEvent> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(replayed, keys(&["b", "a", "b"])?);
+
+        let mut queue = KeyQueue::new(false, 3);
+        queue.push("a".parse()?, &keybindings, &cancel_key);
+        assert!(queue.pop().is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_poll_config_files() -> Result<()> {
+        let dir = TempDir::new("poll-config-files");
+        let config_file = dir.write("config.toml", "interval = 1.0");
+        let included_file = dir.write("included.toml", "interval = 2.0");
+        let (config_files_tx, config_files_rx) = watch::channel(vec![config_file.clone()]);
+        let (event_tx, mut event_rx) = mpsc::channel(10);
+        let poll_task = tokio::spawn(poll_config_files(config_files_rx, event_tx));
+
+        // Set the modification time explicitly, because its resolution might
+        // be too coarse to notice the change.
+        let touch = |path: &Path| -> Result<()> {
+            let file = fs::File::options().write(true).open(path)?;
+            file.set_modified(SystemTime::now() + Duration::from_secs(10))?;
+            Ok(())
+        };
+
+        // Unmodified and unwatched config files aren't reported.
+        touch(&included_file)?;
+        let event = tokio::time::timeout(Duration::from_millis(1500), event_rx.recv()).await;
+        assert!(event.is_err());
+
+        touch(&config_file)?;
+        let event = tokio::time::timeout(Duration::from_secs(3), event_rx.recv()).await?;
+        assert!(matches!(event, Some(Event::ConfigFileChanged)));
+
+        // Newly watched files are reported once they are modified.
+        config_files_tx.send(vec![config_file, included_file.clone()])?;
+        let event = tokio::time::timeout(Duration::from_millis(1500), event_rx.recv()).await;
+        assert!(event.is_err());
+        fs::remove_file(&included_file)?;
+        let event = tokio::time::timeout(Duration::from_secs(3), event_rx.recv()).await?;
+        assert!(matches!(event, Some(Event::ConfigFileChanged)));
+
+        // The task stops once the config files are no longer updated.
+        drop(config_files_tx);
+        tokio::time::timeout(Duration::from_secs(3), poll_task).await??;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_reload() -> Result<()> {
+        let dir = TempDir::new("cancel-reload");
+        let pgid_file = dir.path().join("pgid");
+        let command = test_utils::sleeping_command(&pgid_file);
+        let cli = test_utils::cli(&["--no-default-keybindings", "-b", "r:reload", &command]);
+        let config = Config::from_cli(cli.clone())?;
+        let (mut ui, polling_state) = UI::new(config, cli, TerminalManager::headless()?).await?;
+        let watched_command_task = tokio::spawn(poll_execute_watched_command(
+            polling_state.watched_command,
+            polling_state.watch_rate_rx,
+            ui.channels.event_tx.clone(),
+        ));
+        let first_pgid = test_utils::read_pgid(&pgid_file).await;
+        fs::remove_file(&pgid_file)?;
+
+        // Reloading terminates the first execution, and starts another one.
+        ui.handle_key_event("r".parse()?).await?;
+        assert!(matches!(
+            ui.blocking_state,
+            BlockingState::BlockedReloadingWatchedCommand
+        ));
+        let reloaded_pgid = test_utils::read_pgid(&pgid_file).await;
+        assert!(test_utils::has_exited(first_pgid).await);
+
+        // Cancelling terminates the reloaded execution, too.
+        ui.cancel_blocking().await;
+        assert!(matches!(ui.blocking_state, BlockingState::Unblocked));
+        assert!(test_utils::has_exited(reloaded_pgid).await);
+        assert!(ui.channels.event_rx.try_recv().is_err());
+
+        drop(ui);
+        watched_command_task.await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reload_config() -> Result<()> {
+        let dir = TempDir::new("reload-config");
+        let config_file = dir.write("config.toml", "[keybindings]\n\"a\" = [ \"exit\" ]");
+        let config_file_arg = config_file.to_string_lossy().into_owned();
+        let cli = test_utils::cli(&[
+            "-c",
+            &config_file_arg,
+            "--initial-env",
+            "set-env name -- printf value",
+            "printf 'one\ntwo\nthree\n'",
+        ]);
+        let config = Config::from_cli(cli.clone())?;
+        let (mut ui, _polling_state) = UI::new(config, cli, TerminalManager::headless()?).await?;
+        ui.update_lines(Ok("one\ntwo\nthree\n".to_owned()))?;
+        ui.state.move_down(1);
+        ui.state.select();
+        ui.state.move_down(1);
+
+        let assert_state_is_kept = |ui: &mut UI| {
+            assert_eq!(
+                ui.state.get_cursor_line_and_selected_lines(),
+                Some(("three".to_owned(), "two".to_owned()))
+            );
+            let env_variables = ui.state.get_env();
+            let env_variables = env_variables.try_lock().expect("env isn't locked");
+            assert_eq!(env_variables.expand("$name"), "value");
+        };
+        let key = |key: &str| -> KeyEvent { key.parse().expect("valid key") };
+
+        // An invalid config is displayed as an error, keeping the previous
+        // config and state.
+        dir.write("config.toml", "interval = -1.0");
+        ui.reload_config();
+        let error = ui.state.error_message().expect("error is displayed");
+        assert!(error.starts_with("Failed to reload config file"), "{error}");
+        assert!(ui.keybindings.get_operations(&key("a")).is_some());
+        assert_state_is_kept(&mut ui);
+        draw!(ui)?;
+
+        // Fixing the config applies it, and hides the error.
+        dir.write("config.toml", "[keybindings]\n\"b\" = [ \"exit\" ]");
+        ui.reload_config();
+        assert!(ui.state.error_message().is_none());
+        assert!(ui.keybindings.get_operations(&key("a")).is_none());
+        assert!(ui.keybindings.get_operations(&key("b")).is_some());
+        assert_state_is_kept(&mut ui);
+        draw!(ui)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,246 @@
+use super::{help_menu::centered_rect, lines::Lines, EnvVariable};
+use crate::config::{Columns, Fields, KeyEvent, Styles};
+use anyhow::Result;
+use itertools::{izip, Itertools};
+use ratatui::{
+    prelude::{Backend, Constraint, Direction, Layout},
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// A popup list of options, e.g. the output lines of a command, from which
+/// one or multiple options are chosen as the value of an env variable.
+/// Typing filters the displayed options.
+pub struct Chooser {
+    env_variable: EnvVariable,
+    options: Vec<String>,
+    /// Whether each option is selected, even if it is currently filtered out.
+    selected: Vec<bool>,
+    filter: String,
+    /// The indices of the options that match the filter.
+    matches: Vec<usize>,
+    lines: Lines,
+}
+
+/// What should happen after a key was pressed in the chooser.
+#[derive(Debug, PartialEq)]
+pub enum ChooserAction {
+    /// Keep displaying the chooser.
+    Continue,
+    /// Set the env variable to the chosen options.
+    Choose(EnvVariable, String),
+    /// Close the chooser without choosing anything.
+    Abort,
+}
+
+impl Chooser {
+    pub fn new(env_variable: EnvVariable, options: String, styles: Styles) -> Result<Self> {
+        let options: Vec<String> = options.lines().map(str::to_owned).collect();
+        let lines = Lines::new(
+            Fields::try_new(None, None, 0)?,
+            Columns::default(),
+            styles,
+            0,
+            0,
+            0,
+            false,
+        );
+        let mut chooser = Self {
+            env_variable,
+            selected: vec![false; options.len()],
+            options,
+            filter: String::new(),
+            matches: vec![],
+            lines,
+        };
+        chooser.apply_filter()?;
+        Ok(chooser)
+    }
+
+    pub fn render<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        let area = centered_rect(60, 60, frame.size());
+        let style = Style::default().fg(Color::Cyan);
+        let block = Block::default()
+            .title(format!(
+                "choose {} ({}/{})",
+                self.env_variable,
+                self.matches.len(),
+                self.options.len()
+            ))
+            .borders(Borders::ALL)
+            .border_style(style);
+        let inner_area = block.inner(area);
+
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(inner_area);
+        let filter = Paragraph::new(format!("> {}", self.filter)).style(style);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        frame.render_widget(filter, areas[0]);
+        self.lines.render(frame, areas[1]);
+    }
+
+    /// Handle a key pressed while the chooser is displayed: `enter` chooses
+    /// the selected options (or, if none are selected, the option under the
+    /// cursor), `esc` aborts, `tab` toggles the selection of the option
+    /// under the cursor, and typing edits the filter.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> Result<ChooserAction> {
+        match key.to_string().as_str() {
+            "enter" => return Ok(self.choose()),
+            "esc" => return Ok(ChooserAction::Abort),
+            "up" | "ctrl+p" => self.lines.move_cursor_up(1),
+            "down" | "ctrl+n" => self.lines.move_cursor_down(1),
+            "tab" => {
+                if let Some(selected) = self.cursor_option().map(|i| &mut self.selected[i]) {
+                    *selected = !*selected;
+                }
+                self.apply_selection();
+                self.lines.move_cursor_down(1);
+            }
+            "backspace" => {
+                if self.filter.pop().is_some() {
+                    self.apply_filter()?;
+                }
+            }
+            _ => {
+                if let Some(c) = key.typed_char() {
+                    self.filter.push(c);
+                    self.apply_filter()?;
+                }
+            }
+        }
+        Ok(ChooserAction::Continue)
+    }
+
+    fn choose(&self) -> ChooserAction {
+        let value = if self.selected.contains(&true) {
+            izip!(&self.options, &self.selected)
+                .filter_map(|(option, &selected)| selected.then_some(option))
+                .join("\n")
+        } else {
+            match self.cursor_option() {
+                Some(i) => self.options[i].clone(),
+                // Nothing matches the filter, so there is nothing to choose.
+                None => return ChooserAction::Continue,
+            }
+        };
+        ChooserAction::Choose(self.env_variable.clone(), value)
+    }
+
+    /// The index of the option under the cursor.
+    fn cursor_option(&self) -> Option<usize> {
+        self.lines
+            .cursor_index
+            .and_then(|i| self.matches.get(i).copied())
+    }
+
+    /// Display only the options that match the filter, with the cursor on
+    /// the first one.
+    fn apply_filter(&mut self) -> Result<()> {
+        self.matches = self
+            .options
+            .iter()
+            .enumerate()
+            .filter_map(|(i, option)| matches_filter(option, &self.filter).then_some(i))
+            .collect();
+        self.lines
+            .update_lines(self.matches.iter().map(|&i| &self.options[i]).join("\n"))?;
+        self.apply_selection();
+        self.lines.move_cursor_to_first_line();
+        Ok(())
+    }
+
+    fn apply_selection(&mut self) {
+        self.lines.selected = self.matches.iter().map(|&i| self.selected[i]).collect();
+    }
+}
+
+/// Whether the option contains all whitespace separated words of the filter,
+/// ignoring case.
+fn matches_filter(option: &str, filter: &str) -> bool {
+    let option = option.to_lowercase();
+    filter
+        .to_lowercase()
+        .split_whitespace()
+        .all(|word| option.contains(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chooser(options: &str) -> Chooser {
+        let styles =
+            Styles::parse(None, None, None, None, None, None, None, None, None, None).unwrap();
+        Chooser::new("choice".parse().unwrap(), options.to_owned(), styles).unwrap()
+    }
+
+    /// Press the keys, and return the action of the last key.
+    fn press(chooser: &mut Chooser, keys: &[&str]) -> ChooserAction {
+        keys.iter()
+            .map(|key| chooser.handle_key(&key.parse().unwrap()).unwrap())
+            .last()
+            .unwrap()
+    }
+
+    fn chosen(value: &str) -> ChooserAction {
+        ChooserAction::Choose("choice".parse().unwrap(), value.to_owned())
+    }
+
+    #[test]
+    fn test_choose_cursor_option() {
+        let mut chooser = chooser("apple\nbanana\ncherry");
+        assert_eq!(press(&mut chooser, &["down", "enter"]), chosen("banana"));
+        assert_eq!(press(&mut chooser, &["esc"]), ChooserAction::Abort);
+    }
+
+    #[test]
+    fn test_tab_toggles_and_advances() {
+        let mut chooser = chooser("apple\nbanana\ncherry");
+        assert_eq!(press(&mut chooser, &["tab"]), ChooserAction::Continue);
+        assert_eq!(chooser.selected, [true, false, false]);
+        assert_eq!(chooser.cursor_option(), Some(1));
+
+        press(&mut chooser, &["up", "tab"]);
+        assert_eq!(chooser.selected, [false, false, false]);
+        assert_eq!(chooser.cursor_option(), Some(1));
+    }
+
+    #[test]
+    fn test_filter_keeps_selections() {
+        let mut chooser = chooser("apple\nbanana\ncherry");
+        // Select cherry while apple is filtered out, and apple afterwards.
+        press(&mut chooser, &["c", "h", "tab"]);
+        assert_eq!(chooser.matches, [2]);
+        press(&mut chooser, &["backspace", "backspace"]);
+        assert_eq!(chooser.matches, [0, 1, 2]);
+        assert_eq!(chooser.lines.selected, [false, false, true]);
+        press(&mut chooser, &["tab"]);
+
+        // The options are joined in their original order.
+        assert_eq!(press(&mut chooser, &["enter"]), chosen("apple\ncherry"));
+    }
+
+    #[test]
+    fn test_no_matches() {
+        let mut chooser = chooser("apple\nbanana");
+        assert_eq!(
+            press(&mut chooser, &["x", "y", "enter"]),
+            ChooserAction::Continue
+        );
+        assert!(chooser.matches.is_empty());
+        assert_eq!(chooser.cursor_option(), None);
+    }
+
+    #[test]
+    fn test_matches_filter() {
+        assert!(matches_filter("kube-system", ""));
+        assert!(matches_filter("kube-system", "SYS"));
+        assert!(matches_filter("kube-system", "sys kube"));
+        assert!(!matches_filter("kube-system", "sys default"));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::config::KeyEvent;
+use ratatui::{
+    prelude::{Alignment, Backend, Rect},
+    style::{Color, Style},
+    text::Text,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// A yes/no dialog, which is displayed in the center of the screen until it
+/// is answered.
+pub struct Confirmation {
+    message: String,
+}
+
+impl Confirmation {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+
+    pub fn render<B: Backend>(&self, frame: &mut Frame<B>) {
+        let size = frame.size();
+        let text = format!("{}\n\n[y]es / [n]o", self.message);
+
+        // The box fits the text (including its borders), but is at most as
+        // large as the screen, in which case the text is wrapped.
+        let content_width = text.lines().map(UnicodeWidthStr::width).max().unwrap_or(0) as u16;
+        let width = content_width.saturating_add(4).min(size.width);
+        let content_height = text.lines().count() as u16;
+        let height = content_height.saturating_add(2).min(size.height);
+        let area = Rect {
+            x: size.x + (size.width - width) / 2,
+            y: size.y + (size.height - height) / 2,
+            width,
+            height,
+        };
+
+        let style = Style::default().fg(Color::Yellow);
+        let paragraph = Paragraph::new(Text::raw(text))
+            .block(
+                Block::default()
+                    .title("confirm")
+                    .borders(Borders::ALL)
+                    .border_style(style),
+            )
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
+
+/// The answer that a key gives to a confirmation, if any: `y` and `enter`
+/// confirm, while `n` and `esc` decline.
+pub fn answer(key: &KeyEvent) -> Option<bool> {
+    match key.to_string().as_str() {
+        "y" | "Y" | "enter" => Some(true),
+        "n" | "N" | "esc" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer() {
+        let answer = |key: &str| answer(&key.parse().unwrap());
+        assert_eq!(answer("y"), Some(true));
+        assert_eq!(answer("Y"), Some(true));
+        assert_eq!(answer("enter"), Some(true));
+        assert_eq!(answer("n"), Some(false));
+        assert_eq!(answer("esc"), Some(false));
+        assert_eq!(answer("x"), None);
+        assert_eq!(answer("ctrl+y"), None);
+    }
+}
@@ -1,10 +1,10 @@
 mod env_variable;
 
 use crate::{
-    command::CommandBuilder,
+    command::{CommandBuilder, CommandSettings},
     config::{OperationParsed, OperationsParsed},
 };
-use std::{collections::HashMap, fmt, io::Write};
+use std::{collections::HashMap, env, fmt, io::Write};
 
 use anyhow::{bail, Result};
 pub use env_variable::EnvVariable;
@@ -17,13 +17,16 @@ pub struct EnvVariables(HashMap<EnvVariable, String>);
 impl EnvVariables {
     // TODO: maybe extend to also allow the general execution of normal commands before the initial watched command is executed (if there are use-cases for that)
     /// Receive parsed operations, but only execute the "set-env" operations.
-    pub async fn generate_initial(value: OperationsParsed) -> Result<Self> {
+    pub async fn generate_initial(
+        value: OperationsParsed,
+        settings: &CommandSettings,
+    ) -> Result<Self> {
         // TODO: consider trying to use async iterators to do this in one iterator pass (instead of the mut hashmap) once stable
         let mut map = HashMap::new();
         for op in value.into_iter() {
             match op {
                 OperationParsed::SetEnv(env_variable, command) => {
-                    let output = CommandBuilder::new(command)
+                    let output = CommandBuilder::new(command, settings.clone())
                         .blocking()
                         .with_output()
                         .execute()
@@ -45,6 +48,48 @@ impl EnvVariables {
         self.0.remove(env);
     }
 
+    /// Replace all `$name` and `${name}` in the string with the value of the
+    /// env variable, or, if it isn't set by watchbind, of the process's env
+    /// variable. Unset variables are replaced by the empty string, like in a
+    /// POSIX shell.
+    pub fn expand(&self, s: &str) -> String {
+        let lookup = |name: &str| {
+            let watchbind_value = name
+                .parse::<EnvVariable>()
+                .ok()
+                .and_then(|env_variable| self.0.get(&env_variable).cloned());
+            watchbind_value
+                .or_else(|| env::var(name).ok())
+                .unwrap_or_default()
+        };
+        let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+        let mut expanded = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(dollar) = rest.find('$') {
+            expanded.push_str(&rest[..dollar]);
+            let after_dollar = &rest[dollar + 1..];
+            if let Some(braced) = after_dollar.strip_prefix('{') {
+                if let Some(end) = braced.find('}') {
+                    expanded.push_str(&lookup(&braced[..end]));
+                    rest = &braced[end + 1..];
+                    continue;
+                }
+            }
+            let name_len = after_dollar
+                .find(|c| !is_name_char(c))
+                .unwrap_or(after_dollar.len());
+            if name_len == 0 {
+                expanded.push('$');
+            } else {
+                expanded.push_str(&lookup(&after_dollar[..name_len]));
+            }
+            rest = &after_dollar[name_len..];
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+
     /// Write formatted version (insert elastic tabstops) to a buffer.
     fn write<W: Write>(&self, writer: W) -> Result<()> {
         let mut tw = TabWriter::new(writer);
@@ -89,3 +134,24 @@ impl FromIterator<(EnvVariable, String)> for EnvVariables {
         EnvVariables(iter.into_iter().collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() -> Result<()> {
+        let env_variables: EnvVariables = [
+            ("line".parse()?, "file name".to_owned()),
+            ("dir".parse()?, "/tmp".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(env_variables.expand("$dir/$line"), "/tmp/file name");
+        assert_eq!(env_variables.expand("${dir}_x/${line}"), "/tmp_x/file name");
+        assert_eq!(env_variables.expand("$dir_x/$"), "/$");
+        assert_eq!(env_variables.expand("${dir"), "${dir");
+        assert_eq!(env_variables.expand("a$-b"), "a$-b");
+        Ok(())
+    }
+}
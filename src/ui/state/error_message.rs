@@ -0,0 +1,85 @@
+use ratatui::{
+    prelude::{Alignment, Backend, Rect},
+    style::{Color, Style},
+    text::Text,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// An error that doesn't stop watchbind, e.g. an invalid config file, which
+/// is displayed at the bottom of the screen until it is resolved. It can also
+/// be an informational message, e.g. the summary of an `exec-each` operation.
+pub struct ErrorMessage {
+    source: ErrorSource,
+    message: String,
+    is_error: bool,
+}
+
+/// What caused an error, which determines what resolves it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ErrorSource {
+    /// Resolved by successfully reloading the config file.
+    ConfigFile,
+    /// Resolved by the next successful execution of the watched command.
+    WatchedCommand,
+    /// Resolved by the next key press.
+    Subcommand,
+}
+
+impl ErrorMessage {
+    pub fn new(source: ErrorSource, message: String) -> Self {
+        Self {
+            source,
+            message,
+            is_error: true,
+        }
+    }
+
+    pub fn new_info(source: ErrorSource, message: String) -> Self {
+        Self {
+            source,
+            message,
+            is_error: false,
+        }
+    }
+
+    pub fn source(&self) -> ErrorSource {
+        self.source
+    }
+
+    #[cfg(test)]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn render<B: Backend>(&self, frame: &mut Frame<B>) {
+        let size = frame.size();
+
+        // The box is at most half the screen high, including its borders.
+        let content_height = self.message.lines().count() as u16;
+        let height = (content_height + 2).min(size.height / 2).max(3);
+        let area = Rect {
+            y: size.height.saturating_sub(height),
+            height: height.min(size.height),
+            ..size
+        };
+
+        let (title, color) = match self.is_error {
+            true => ("error", Color::Red),
+            false => ("info", Color::Green),
+        };
+        let style = Style::default().fg(color);
+        let paragraph = Paragraph::new(Text::raw(self.message.as_str()))
+            .block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_style(style),
+            )
+            .alignment(Alignment::Left)
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
@@ -113,11 +113,15 @@ impl HelpMenu {
     pub fn hide(&mut self) {
         self.update_vertical_scroll_index(0);
     }
+
+    pub fn update_keybindings(&mut self, keybindings_str: String) {
+        self.keybindings_str = keybindings_str;
+    }
 }
 
 /// Helper function to create a centered rect using up certain percentage
 /// of the available rect `r`
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+pub(super) fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
@@ -1,23 +1,105 @@
-use ratatui::{style::Style, widgets::Cell};
+use super::{COLUMN_GAP, MARKER_GAP};
+use crate::config::Columns;
+use ratatui::{
+    style::Style,
+    text::{Line as TextLine, Span, Text},
+    widgets::{Cell, Row},
+};
+use std::borrow::Cow;
+use unicode_width::UnicodeWidthChar;
 
 pub struct Line {
     unformatted: String,
-    formatted: Option<String>,
+    /// The formatted cells of the line, one for each displayed column.
+    cells: Vec<String>,
     style: Style,
 }
 
+/// A horizontally (partially) visible part of the table, which is either a
+/// column or the gap preceding it.
+pub struct VisibleSegment {
+    /// The index of the column, or None for a gap.
+    pub column: Option<usize>,
+    /// The width cut off from the left, due to horizontal scrolling.
+    pub skipped: usize,
+    /// The visible width.
+    pub width: usize,
+}
+
 impl Line {
-    pub fn new(unformatted: String, formatted: Option<String>, style: Style) -> Self {
+    pub fn new(unformatted: String, cells: Vec<String>, style: Style) -> Self {
         Self {
             unformatted,
-            formatted,
+            cells,
             style,
         }
     }
 
-    pub fn draw(&self) -> Cell {
-        let line = self.formatted.as_ref().unwrap_or(&self.unformatted);
-        Cell::from(" ".to_owned() + line).style(self.style)
+    /// Draw the visible segments of the line as a table row, which starts
+    /// with the selection marker. The column styles are only applied if
+    /// present.
+    pub fn draw(
+        &self,
+        selected_style: Style,
+        columns: Option<&Columns>,
+        segments: &[VisibleSegment],
+    ) -> Row<'_> {
+        let marker = Cell::from(" ").style(selected_style);
+        let cells = segments.iter().map(|segment| match segment.column {
+            Some(idx) => {
+                let cell = self.cells.get(idx).map_or("", String::as_str);
+                let style = columns
+                    .map(|columns| columns.style(idx))
+                    .unwrap_or_default();
+                Cell::from(skip_width(cell, segment.skipped)).style(style)
+            }
+            None => Cell::default(),
+        });
+        Row::new([marker].into_iter().chain(cells)).style(self.style)
+    }
+
+    /// Draw the whole line as a table row, which starts with the selection
+    /// marker, and wraps the columns over multiple rows if they are wider
+    /// than the given width. Returns the row along with its height.
+    pub fn draw_wrapped(
+        &self,
+        selected_style: Style,
+        columns: Option<&Columns>,
+        width: usize,
+    ) -> (Row<'_>, u16) {
+        let marker = Cell::from(" ").style(selected_style);
+
+        let gaps = [MARKER_GAP]
+            .into_iter()
+            .chain(std::iter::repeat(COLUMN_GAP));
+        let last_idx = self.cells.len().saturating_sub(1);
+        let spans = self
+            .cells
+            .iter()
+            .zip(gaps)
+            .enumerate()
+            .flat_map(|(idx, (cell, gap))| {
+                let style = columns
+                    .map(|columns| columns.style(idx))
+                    .unwrap_or_default();
+                // Trailing padding would otherwise be wrapped into empty rows.
+                let cell = if idx == last_idx {
+                    cell.trim_end()
+                } else {
+                    cell
+                };
+                [
+                    (" ".repeat(gap), Style::default()),
+                    (cell.to_owned(), style),
+                ]
+            });
+        let text = wrap(spans, width);
+        let height = text.height() as u16;
+
+        let row = Row::new([marker, Cell::from(text)])
+            .height(height)
+            .style(self.style);
+        (row, height)
     }
 
     pub fn update_style(&mut self, style: Style) {
@@ -28,3 +110,80 @@ impl Line {
         &self.unformatted
     }
 }
+
+/// Remove the given width from the start of a string. Wide characters that
+/// are only partially skipped are replaced with spaces.
+fn skip_width(s: &str, width: usize) -> Cow<'_, str> {
+    let mut skipped = 0;
+    for (idx, c) in s.char_indices() {
+        if skipped >= width {
+            return if skipped == width {
+                Cow::Borrowed(&s[idx..])
+            } else {
+                Cow::Owned(" ".repeat(skipped - width) + &s[idx..])
+            };
+        }
+        skipped += c.width().unwrap_or(0);
+    }
+    Cow::Owned(" ".repeat(skipped.saturating_sub(width)))
+}
+
+/// Wrap styled text into lines that are at most the given width wide.
+fn wrap(spans: impl Iterator<Item = (String, Style)>, width: usize) -> Text<'static> {
+    let mut lines = vec![];
+    let mut line = vec![];
+    let mut line_width = 0;
+    for (content, style) in spans {
+        let mut chunk = String::new();
+        for c in content.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if line_width + char_width > width && line_width > 0 {
+                line.push(Span::styled(std::mem::take(&mut chunk), style));
+                lines.push(TextLine::from(std::mem::take(&mut line)));
+                line_width = 0;
+            }
+            chunk.push(c);
+            line_width += char_width;
+        }
+        line.push(Span::styled(chunk, style));
+    }
+    lines.push(TextLine::from(line));
+    Text::from(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_width() {
+        assert_eq!(skip_width("abc", 0), "abc");
+        assert_eq!(skip_width("abc", 2), "c");
+        assert_eq!(skip_width("abc", 5), "");
+        // Partially skipped wide characters are replaced with spaces.
+        assert_eq!(skip_width("日本", 1), " 本");
+        assert_eq!(skip_width("日本", 2), "本");
+        assert_eq!(skip_width("日本", 3), " ");
+        assert_eq!(skip_width("日本", 4), "");
+    }
+
+    #[test]
+    fn test_wrap() {
+        let wrapped = |spans: &[&str], width: usize| -> Vec<String> {
+            let spans = spans
+                .iter()
+                .map(|&span| (span.to_owned(), Style::default()));
+            wrap(spans, width)
+                .lines
+                .iter()
+                .map(|line| line.spans.iter().map(|span| &*span.content).collect())
+                .collect()
+        };
+        assert_eq!(wrapped(&["ab", "cde"], 3), ["abc", "de"]);
+        assert_eq!(wrapped(&["ab", "cd"], 4), ["abcd"]);
+        // Wide characters are never split.
+        assert_eq!(wrapped(&["a日本"], 2), ["a", "日", "本"]);
+        // Characters wider than the width get a line of their own.
+        assert_eq!(wrapped(&["ab"], 0), ["a", "b"]);
+    }
+}
@@ -1,47 +1,115 @@
 mod line;
 
-pub use line::Line;
+pub use line::{Line, VisibleSegment};
 
-use crate::config::Styles;
-use crate::config::{Fields, TableFormatter};
+use crate::config::{Columns, Fields, Styles, TableFormatter};
 use anyhow::Result;
-use itertools::izip;
+use itertools::{izip, Itertools};
 use ratatui::{
-    prelude::{Backend, Constraint},
+    prelude::{Backend, Constraint, Rect},
+    prelude::{Direction, Layout},
     style::Style,
-    widgets::{Cell, Row, Table, TableState},
+    widgets::{Row, Table, TableState},
     Frame,
 };
-use std::cmp::max;
+use std::ops::Range;
+
+/// A single field (i.e. column) of the line under the cursor and the
+/// selected lines.
+pub struct SelectedField {
+    /// The field's index, starting at 1.
+    pub number: usize,
+    /// The field's name in the first header line, if present.
+    pub header: Option<String>,
+    /// The field's value in the line under the cursor.
+    pub cursor_line: String,
+    /// The field's values in all selected lines, separated by newlines.
+    pub selected_lines: String,
+}
+
+/// The width of the selection marker column.
+const MARKER_WIDTH: usize = 1;
+
+/// The width of the gap between the selection marker and the first column.
+const MARKER_GAP: usize = 1;
+
+/// The width of the gap between two columns.
+const COLUMN_GAP: usize = 2;
 
 pub struct Lines {
     pub lines: Vec<Line>,
     pub selected: Vec<bool>,
     pub styles: Styles,
     pub fields: Fields,
+    pub columns: Columns,
+    /// The widths of all columns, shared by all lines.
+    pub column_widths: Vec<usize>,
+    /// The number of leading columns that are never scrolled horizontally.
+    pub frozen_columns: usize,
+    /// The width that is scrolled past horizontally.
+    pub horizontal_offset: usize,
+    /// Whether lines wider than the screen are wrapped over multiple rows.
+    pub wrap_lines: bool,
     pub index_after_header_lines: usize,
+    /// The number of lines at the end that are displayed as sticky footer.
+    pub footer_lines: usize,
     pub cursor_index: Option<usize>,
     // TODO: deprecate in future
     pub table_state: TableState,
 }
 
 impl Lines {
-    pub fn new(fields: Fields, styles: Styles, header_lines: usize) -> Self {
+    pub fn new(
+        fields: Fields,
+        columns: Columns,
+        styles: Styles,
+        header_lines: usize,
+        footer_lines: usize,
+        frozen_columns: usize,
+        wrap_lines: bool,
+    ) -> Self {
         Self {
             lines: vec![],
             selected: vec![],
             fields,
+            columns,
+            column_widths: vec![],
+            frozen_columns,
+            horizontal_offset: 0,
+            wrap_lines,
             cursor_index: None,
             styles,
             index_after_header_lines: header_lines,
+            footer_lines,
             table_state: TableState::default(),
         }
     }
 
-    pub fn render<B: Backend>(&mut self, frame: &mut Frame<B>) {
+    pub fn render<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let content_width = (area.width as usize).saturating_sub(MARKER_WIDTH);
+
+        // When wrapping lines, all columns are drawn as a single wrapped
+        // cell, so horizontal scrolling doesn't apply.
+        let (segments, widths) = if self.wrap_lines {
+            let widths = vec![
+                Constraint::Length(MARKER_WIDTH as u16),
+                Constraint::Length(content_width as u16),
+            ];
+            (None, widths)
+        } else {
+            let segments = self.visible_segments(content_width);
+            let widths = [MARKER_WIDTH]
+                .into_iter()
+                .chain(segments.iter().map(|segment| segment.width))
+                .map(|width| Constraint::Length(width as u16))
+                .collect();
+            (Some(segments), widths)
+        };
+
         // TODO: do as much as possible in update_lines to improve performance
-        let rows: Vec<Row> = izip!(&self.lines, &self.selected)
-            .map(|(line, &selected)| {
+        let mut rows: Vec<(Row, u16)> = izip!(&self.lines, &self.selected)
+            .enumerate()
+            .map(|(i, (line, &selected))| {
                 // TODO: consider replacing Vec<bool> with Vec<Style> directly
                 let selected_style = if selected {
                     self.styles.selected
@@ -49,58 +117,172 @@ impl Lines {
                     self.styles.line
                 };
 
-                Row::new(vec![Cell::from(" ").style(selected_style), line.draw()])
+                // The cursor, header and footer styles take precedence over
+                // the column styles.
+                let is_cursor = self.cursor_index == Some(i);
+                let columns = (!self.is_sticky(i) && !is_cursor).then_some(&self.columns);
+
+                match &segments {
+                    Some(segments) => (line.draw(selected_style, columns, segments), 1),
+                    None => line.draw_wrapped(selected_style, columns, content_width),
+                }
             })
             .collect();
 
-        let table = Table::new(rows)
-            .widths(&[Constraint::Length(1), Constraint::Percentage(100)])
-            .column_spacing(0);
-
-        frame.render_stateful_widget(table, frame.size(), &mut self.table_state);
+        // The header and footer lines are rendered in fixed areas, so that
+        // only the body in between is scrolled.
+        let body = self.body();
+        let footer_rows = rows.split_off(body.end);
+        let body_rows = rows.split_off(body.start);
+        let header_rows = rows;
+
+        let height = |rows: &[(Row, u16)]| rows.iter().map(|(_, height)| height).sum::<u16>();
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(height(&header_rows)),
+                Constraint::Min(0),
+                Constraint::Length(height(&footer_rows)),
+            ])
+            .split(area);
+
+        fn table<'a>(rows: Vec<(Row<'a>, u16)>, widths: &'a [Constraint]) -> Table<'a> {
+            Table::new(rows.into_iter().map(|(row, _)| row))
+                .widths(widths)
+                .column_spacing(0)
+        }
+        frame.render_widget(table(header_rows, &widths), areas[0]);
+        frame.render_stateful_widget(table(body_rows, &widths), areas[1], &mut self.table_state);
+        frame.render_widget(table(footer_rows, &widths), areas[2]);
     }
 
-    // TODO: might be better suited as a new() method or similar
-    pub fn update_lines(&mut self, lines: String) -> Result<()> {
-        let formatted: Vec<Option<String>> = match lines.as_str().format_as_table(&self.fields)? {
-            Some(formatted) => formatted.lines().map(str::to_owned).map(Some).collect(),
-            None => vec![None; lines.lines().count()],
-        };
+    /// The visible parts of all columns, each preceded by a gap, given the
+    /// available width and the horizontal scroll offset. The frozen columns
+    /// are never scrolled, and the remaining columns are cut off from left
+    /// to right once the available width is exhausted.
+    fn visible_segments(&mut self, available_width: usize) -> Vec<VisibleSegment> {
+        let gaps = [MARKER_GAP]
+            .into_iter()
+            .chain(std::iter::repeat(COLUMN_GAP));
+        let segments: Vec<(Option<usize>, usize)> = izip!(gaps, &self.column_widths)
+            .enumerate()
+            .flat_map(|(idx, (gap, &width))| [(None, gap), (Some(idx), width)])
+            .collect();
 
-        self.lines = izip!(lines.lines(), formatted)
+        // Prevent scrolling further than the end of the last column.
+        let frozen_segments = 2 * self.frozen_columns;
+        let frozen_width: usize = segments
+            .iter()
+            .take(frozen_segments)
+            .map(|(_, width)| width)
+            .sum();
+        let scrollable_width: usize = segments
+            .iter()
+            .skip(frozen_segments)
+            .map(|(_, width)| width)
+            .sum();
+        let scrollable_area = available_width.saturating_sub(frozen_width);
+        self.horizontal_offset = self
+            .horizontal_offset
+            .min(scrollable_width.saturating_sub(scrollable_area));
+
+        let mut remaining_offset = self.horizontal_offset;
+        let mut remaining_width = available_width;
+        segments
+            .into_iter()
             .enumerate()
-            .map(|(i, (unformatted, formatted))| {
-                let style = if i < self.index_after_header_lines {
-                    self.styles.header
+            .filter_map(|(i, (column, width))| {
+                let skipped = if i < frozen_segments {
+                    0
                 } else {
-                    self.styles.line
+                    let skipped = remaining_offset.min(width);
+                    remaining_offset -= skipped;
+                    skipped
                 };
+                let visible_width = (width - skipped).min(remaining_width);
+                remaining_width -= visible_width;
+
+                (visible_width > 0).then_some(VisibleSegment {
+                    column,
+                    skipped,
+                    width: visible_width,
+                })
+            })
+            .collect()
+    }
+
+    // TODO: might be better suited as a new() method or similar
+    pub fn update_lines(&mut self, lines: String) -> Result<()> {
+        // Without a field separator, every line is a single column.
+        let table: Vec<Vec<&str>> = match lines.as_str().format_as_table(&self.fields) {
+            Some(table) => table,
+            None => lines.lines().map(|line| vec![line]).collect(),
+        };
+        self.column_widths = self.columns.widths(&table);
 
-                Line::new(unformatted.to_owned(), formatted, style)
+        self.lines = izip!(lines.lines(), &table)
+            .map(|(unformatted, row)| {
+                let cells = self.columns.format_row(row, &self.column_widths);
+                Line::new(unformatted.to_owned(), cells, self.styles.line)
             })
             .collect();
 
         self.selected.resize(self.lines.len(), false);
+        // Only lines in the body can be selected, but the header and footer
+        // lines might have been selected before the config was reloaded.
+        let body = self.body();
+        for i in (0..body.start).chain(body.end..self.lines.len()) {
+            self.update_line_style(i, self.styles.header);
+            self.selected[i] = false;
+        }
+
         self.calibrate_cursor();
 
         Ok(())
     }
 
+    /// Replace the display settings, and reformat the current lines
+    /// accordingly, while keeping the cursor and selection.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_config(
+        &mut self,
+        fields: Fields,
+        columns: Columns,
+        styles: Styles,
+        header_lines: usize,
+        footer_lines: usize,
+        frozen_columns: usize,
+        wrap_lines: bool,
+    ) -> Result<()> {
+        self.fields = fields;
+        self.columns = columns;
+        self.styles = styles;
+        self.index_after_header_lines = header_lines;
+        self.footer_lines = footer_lines;
+        self.frozen_columns = frozen_columns;
+        self.wrap_lines = wrap_lines;
+
+        let lines = self.lines.iter().map(Line::unformatted).join("\n");
+        self.update_lines(lines)
+    }
+
     // Moving cursor
 
     // TODO: don't use isize, instead use an enum Up|Down and saturating_{add,sub}
     fn move_cursor(&mut self, index: isize) {
         let old = self.get_cursor_position();
-        let new = if self.lines.is_empty() {
+        let body = self.body();
+        let new = if body.is_empty() {
             None
         } else {
-            let first = self.index_after_header_lines as isize;
-            let last = self.last_index() as isize;
+            let first = body.start as isize;
+            let last = (body.end - 1) as isize;
             Some(index.clamp(first, last) as usize)
         };
 
         self.cursor_index = new;
-        self.table_state.select(self.cursor_index);
+        // The table state only refers to the body, not the header lines.
+        self.table_state.select(new.map(|i| i - body.start));
         self.adjust_cursor_style(old, new);
     }
 
@@ -132,14 +314,31 @@ impl Lines {
     }
 
     pub fn move_cursor_to_last_line(&mut self) {
-        self.move_cursor(self.last_index() as isize);
+        self.move_cursor(self.body().end as isize - 1);
+    }
+
+    // Scrolling horizontally
+
+    pub fn scroll_left(&mut self, steps: usize) {
+        self.horizontal_offset = self.horizontal_offset.saturating_sub(steps);
+    }
+
+    /// The offset is limited to the width of the columns on the next render.
+    pub fn scroll_right(&mut self, steps: usize) {
+        self.horizontal_offset = self.horizontal_offset.saturating_add(steps);
+    }
+
+    // Wrapping lines
+
+    pub fn toggle_wrap_lines(&mut self) {
+        self.wrap_lines = !self.wrap_lines;
     }
 
     // Styling cursor
 
     fn adjust_cursor_style(&mut self, old: Option<usize>, new: Option<usize>) {
         if let Some(old_index) = old {
-            self.update_line_style(old_index, self.styles.line);
+            self.update_line_style(old_index, self.line_style(old_index));
         }
         if let Some(new_index) = new {
             self.update_line_style(new_index, self.styles.cursor);
@@ -178,8 +377,10 @@ impl Lines {
         }
     }
 
+    /// Select all lines, except for the header and footer lines.
     pub fn select_all(&mut self) {
-        self.selected.fill(true);
+        let body = self.body();
+        self.selected[body].fill(true);
     }
 
     pub fn unselect_all(&mut self) {
@@ -188,7 +389,7 @@ impl Lines {
 
     // Getting selected lines
 
-    fn get_line_under_cursor(&self) -> Option<String> {
+    pub fn get_line_under_cursor(&self) -> Option<String> {
         self.get_cursor_position()
             .and_then(|i| self.get_unformatted(i))
     }
@@ -208,6 +409,59 @@ impl Lines {
         })
     }
 
+    // Getting fields of selected lines
+
+    /// Split the line under the cursor and the selected lines (or, if none are
+    /// selected, the line under the cursor) into their fields. Fields are
+    /// split identically to how they are displayed. Returns None if no field
+    /// separator is configured.
+    pub fn get_selected_fields(&self) -> Option<Vec<SelectedField>> {
+        let cursor_index = self.get_cursor_position()?;
+        let cursor_line = self.lines.get(cursor_index)?.unformatted();
+        let cursor_fields: Vec<&str> = self.fields.split(cursor_line)?.collect();
+
+        let selected_fields: Vec<Vec<&str>> = if self.selected.contains(&true) {
+            izip!(&self.lines, &self.selected)
+                .filter_map(|(line, &selected)| selected.then_some(line.unformatted()))
+                .filter_map(|line| self.fields.split(line))
+                .map(Iterator::collect)
+                .collect()
+        } else {
+            vec![cursor_fields.clone()]
+        };
+
+        // The first header line, if present, names the fields.
+        let header_fields: Vec<&str> = match self.index_after_header_lines {
+            0 => vec![],
+            _ => self
+                .lines
+                .first()
+                .and_then(|line| self.fields.split(line.unformatted()))
+                .map(Iterator::collect)
+                .unwrap_or_default(),
+        };
+
+        let field_count = selected_fields
+            .iter()
+            .map(Vec::len)
+            .chain([cursor_fields.len()])
+            .max()
+            .unwrap_or(0);
+
+        let fields = (0..field_count)
+            .map(|idx| SelectedField {
+                number: idx + 1,
+                header: header_fields.get(idx).map(|&header| header.to_owned()),
+                cursor_line: cursor_fields.get(idx).copied().unwrap_or("").to_owned(),
+                selected_lines: selected_fields
+                    .iter()
+                    .map(|fields| fields.get(idx).copied().unwrap_or(""))
+                    .join("\n"),
+            })
+            .collect();
+        Some(fields)
+    }
+
     // Formatting
 
     pub fn unformatted(&self) -> Vec<&String> {
@@ -220,11 +474,201 @@ impl Lines {
 
     // Miscellaneous
 
-    fn last_index(&self) -> usize {
-        if self.lines.is_empty() {
-            self.index_after_header_lines
+    /// The range of the lines between the header and footer lines, which are
+    /// the only lines the cursor can be on.
+    fn body(&self) -> Range<usize> {
+        let start = self.index_after_header_lines.min(self.lines.len());
+        let end = self
+            .lines
+            .len()
+            .saturating_sub(self.footer_lines)
+            .max(start);
+        start..end
+    }
+
+    /// Whether the line at the given index is a header or footer line.
+    fn is_sticky(&self, index: usize) -> bool {
+        !self.body().contains(&index)
+    }
+
+    /// The style of the line at the given index, ignoring the cursor.
+    fn line_style(&self, index: usize) -> Style {
+        if self.is_sticky(index) {
+            self.styles.header
         } else {
-            max(self.index_after_header_lines, self.lines.len() - 1)
+            self.styles.line
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_lines(fields: Fields, header_lines: usize, footer_lines: usize, output: &str) -> Lines {
+        let styles =
+            Styles::parse(None, None, None, None, None, None, None, None, None, None).unwrap();
+        let mut lines = Lines::new(
+            fields,
+            Columns::default(),
+            styles,
+            header_lines,
+            footer_lines,
+            0,
+            false,
+        );
+        lines.update_lines(output.to_owned()).unwrap();
+        lines
+    }
+
+    #[test]
+    fn test_get_selected_fields() {
+        let mut lines = new_lines(Fields::separated_by(","), 1, 0, "NAME,AGE\na,1\nb\nc,3");
+        let fields = lines.get_selected_fields().unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].number, 1);
+        assert_eq!(fields[0].header.as_deref(), Some("NAME"));
+        assert_eq!(fields[0].cursor_line, "a");
+        assert_eq!(fields[1].selected_lines, "1");
+
+        // Missing fields of some lines are empty.
+        lines.move_cursor_down(1);
+        lines.select_current();
+        lines.move_cursor_down(1);
+        lines.select_current();
+        let fields = lines.get_selected_fields().unwrap();
+        assert_eq!(fields[1].cursor_line, "3");
+        assert_eq!(fields[0].selected_lines, "b\nc");
+        assert_eq!(fields[1].selected_lines, "\n3");
+
+        // Without header lines, fields are unnamed.
+        let lines = new_lines(Fields::separated_by(","), 0, 0, "NAME,AGE\na,1");
+        let fields = lines.get_selected_fields().unwrap();
+        assert!(fields.iter().all(|field| field.header.is_none()));
+
+        let lines = new_lines(Fields::try_new(None, None, 0).unwrap(), 0, 0, "a,1");
+        assert!(lines.get_selected_fields().is_none());
+    }
+
+    #[test]
+    fn test_body() {
+        let lines = new_lines(
+            Fields::try_new(None, None, 0).unwrap(),
+            1,
+            2,
+            "h\na\nb\nf\nf",
+        );
+        assert_eq!(lines.body(), 1..3);
+        assert!(lines.is_sticky(0));
+        assert!(!lines.is_sticky(1));
+        assert!(!lines.is_sticky(2));
+        assert!(lines.is_sticky(3));
+        assert!(lines.is_sticky(4));
+
+        // Header and footer lines can exceed the output.
+        let mut lines = new_lines(Fields::try_new(None, None, 0).unwrap(), 2, 2, "h\nh\nf");
+        assert_eq!(lines.body(), 2..2);
+        assert!((0..3).all(|i| lines.is_sticky(i)));
+        assert_eq!(lines.cursor_index, None);
+        assert_eq!(lines.table_state.selected(), None);
+        lines.select_all();
+        assert!(lines.get_selected_lines().is_none());
+        assert!(!lines.selected.contains(&true));
+    }
+
+    #[test]
+    fn test_cursor_in_body() {
+        let mut lines = new_lines(
+            Fields::try_new(None, None, 0).unwrap(),
+            2,
+            1,
+            "h\nh\na\nb\nf",
+        );
+        assert_eq!(lines.cursor_index, Some(2));
+        // The table state is relative to the body.
+        assert_eq!(lines.table_state.selected(), Some(0));
+        lines.move_cursor_down(5);
+        assert_eq!(lines.cursor_index, Some(3));
+        assert_eq!(lines.table_state.selected(), Some(1));
+        lines.move_cursor_to_first_line();
+        assert_eq!(lines.cursor_index, Some(2));
+
+        lines.select_all();
+        assert_eq!(lines.selected, [false, false, true, true, false]);
+        assert_eq!(lines.get_selected_lines().unwrap().1, "a\nb");
+    }
+
+    #[test]
+    fn test_selection_in_body() {
+        let fields = || Fields::try_new(None, None, 0).unwrap();
+        let mut lines = new_lines(fields(), 0, 0, "h\na\nb\nf");
+        lines.select_all();
+        lines
+            .update_config(
+                fields(),
+                Columns::default(),
+                lines.styles.clone(),
+                1,
+                1,
+                0,
+                false,
+            )
+            .unwrap();
+        assert_eq!(lines.selected, [false, true, true, false]);
+        assert_eq!(lines.get_selected_lines().unwrap().1, "a\nb");
+    }
+
+    #[test]
+    fn test_visible_segments() {
+        // The columns are 3, 2 and 1 wide, and preceded by gaps of 1, 2 and 2.
+        let mut lines = new_lines(Fields::separated_by(","), 0, 0, "aaa,bb,c");
+        fn segments(
+            lines: &mut Lines,
+            frozen_columns: usize,
+            offset: usize,
+            width: usize,
+        ) -> Vec<(Option<usize>, usize, usize)> {
+            lines.frozen_columns = frozen_columns;
+            lines.horizontal_offset = offset;
+            lines
+                .visible_segments(width)
+                .into_iter()
+                .map(|segment| (segment.column, segment.skipped, segment.width))
+                .collect()
+        }
+
+        assert_eq!(
+            segments(&mut lines, 0, 0, 20),
+            [
+                (None, 0, 1),
+                (Some(0), 0, 3),
+                (None, 0, 2),
+                (Some(1), 0, 2),
+                (None, 0, 2),
+                (Some(2), 0, 1),
+            ]
+        );
+        // Columns are cut off once the width is exhausted.
+        assert_eq!(
+            segments(&mut lines, 0, 0, 5),
+            [(None, 0, 1), (Some(0), 0, 3), (None, 0, 1)]
+        );
+        // Scrolling past the end of the line stops at the last column.
+        assert_eq!(
+            segments(&mut lines, 0, 100, 4),
+            [(Some(1), 1, 1), (None, 0, 2), (Some(2), 0, 1)]
+        );
+        assert_eq!(lines.horizontal_offset, 7);
+        // Frozen columns aren't scrolled.
+        assert_eq!(
+            segments(&mut lines, 1, 2, 7),
+            [(None, 0, 1), (Some(0), 0, 3), (Some(1), 0, 2), (None, 0, 1)]
+        );
+        // If all columns are frozen, nothing can be scrolled.
+        assert_eq!(
+            segments(&mut lines, 5, 4, 5),
+            [(None, 0, 1), (Some(0), 0, 3), (None, 0, 1)]
+        );
+        assert_eq!(lines.horizontal_offset, 0);
+    }
+}
@@ -1,22 +1,52 @@
+mod chooser;
+mod confirmation;
 mod env_variables;
+mod error_message;
 mod help_menu;
 mod lines;
 
 use std::sync::Arc;
 
-use self::{help_menu::HelpMenu, lines::Lines};
-use crate::config::{Fields, Styles};
+use self::{
+    chooser::Chooser, confirmation::Confirmation, error_message::ErrorMessage, help_menu::HelpMenu,
+    lines::Lines,
+};
+use crate::command::WorkingDir;
+use crate::config::{Columns, Fields, KeyEvent, Styles};
 use anyhow::Result;
-use ratatui::{backend::Backend, Frame};
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    widgets::Paragraph,
+    Frame,
+};
+use std::{collections::HashSet, path::Path};
 
+pub use chooser::ChooserAction;
+pub use confirmation::answer as confirmation_answer;
 pub use env_variables::{EnvVariable, EnvVariables};
+pub use error_message::ErrorSource;
 use tokio::sync::Mutex;
 
 pub struct State {
     mode: Mode,
     lines: Lines,
     help_menu: HelpMenu,
+    /// An error that is displayed on top of the lines, if present.
+    error_message: Option<ErrorMessage>,
+    /// A list of options to choose an env variable's value from, which is
+    /// displayed on top of the lines, if present.
+    chooser: Option<Chooser>,
+    /// A dialog that is displayed on top of everything else, if present.
+    confirmation: Option<Confirmation>,
     pub env_variables: Arc<Mutex<EnvVariables>>,
+    /// The env variables that were set for the fields of the previous
+    /// cursor line and selected lines.
+    field_env_variables: Vec<EnvVariable>,
+    /// The working directory of all commands, which is displayed in a status
+    /// bar once it is set.
+    working_dir: WorkingDir,
 }
 
 #[derive(Default)]
@@ -27,27 +57,157 @@ enum Mode {
 }
 
 impl State {
+    // TODO: clippy says too many arguments, maybe group the Lines settings
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         header_lines: usize,
+        footer_lines: usize,
         fields: Fields,
+        columns: Columns,
+        frozen_columns: usize,
+        wrap_lines: bool,
         styles: Styles,
         keybindings_str: String,
         env_variables: EnvVariables,
+        working_dir: WorkingDir,
     ) -> Self {
         let env_variables = Arc::new(Mutex::new(env_variables));
         Self {
             mode: Mode::default(),
-            lines: Lines::new(fields, styles, header_lines),
+            lines: Lines::new(
+                fields,
+                columns,
+                styles,
+                header_lines,
+                footer_lines,
+                frozen_columns,
+                wrap_lines,
+            ),
             help_menu: HelpMenu::new(keybindings_str, env_variables.clone()),
+            error_message: None,
+            chooser: None,
+            confirmation: None,
             env_variables,
+            field_env_variables: vec![],
+            working_dir,
         }
     }
 
     pub fn draw<B: Backend>(&mut self, frame: &mut Frame<B>) {
-        self.lines.render(frame);
+        let lines_area = match self.working_dir.get() {
+            Some(working_dir) => {
+                let areas = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(1), Constraint::Min(0)])
+                    .split(frame.size());
+                let status_bar = Paragraph::new(working_dir.display().to_string())
+                    .style(Style::default().add_modifier(Modifier::REVERSED));
+                frame.render_widget(status_bar, areas[0]);
+                areas[1]
+            }
+            None => frame.size(),
+        };
+        self.lines.render(frame, lines_area);
         if let Mode::HelpMenu = self.mode {
             self.help_menu.render(frame);
         }
+        if let Some(chooser) = &mut self.chooser {
+            chooser.render(frame);
+        }
+        if let Some(error_message) = &self.error_message {
+            error_message.render(frame);
+        }
+        if let Some(confirmation) = &self.confirmation {
+            confirmation.render(frame);
+        }
+    }
+
+    /// Replace the settings of the lines and the keybindings displayed in
+    /// the help menu.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_config(
+        &mut self,
+        header_lines: usize,
+        footer_lines: usize,
+        fields: Fields,
+        columns: Columns,
+        frozen_columns: usize,
+        wrap_lines: bool,
+        styles: Styles,
+        keybindings_str: String,
+    ) -> Result<()> {
+        self.help_menu.update_keybindings(keybindings_str);
+        self.lines.update_config(
+            fields,
+            columns,
+            styles,
+            header_lines,
+            footer_lines,
+            frozen_columns,
+            wrap_lines,
+        )
+    }
+
+    // API for Error Message
+
+    pub fn show_error(&mut self, source: ErrorSource, message: String) {
+        self.error_message = Some(ErrorMessage::new(source, message));
+    }
+
+    /// Show an informational message in place of an error message, which is
+    /// hidden the same way.
+    pub fn show_info(&mut self, source: ErrorSource, message: String) {
+        self.error_message = Some(ErrorMessage::new_info(source, message));
+    }
+
+    /// The message of the displayed error, if any.
+    #[cfg(test)]
+    pub fn error_message(&self) -> Option<&str> {
+        self.error_message.as_ref().map(ErrorMessage::message)
+    }
+
+    /// Hide the error message, if it was caused by the given source.
+    pub fn hide_error(&mut self, source: ErrorSource) {
+        if let Some(error_message) = &self.error_message {
+            if error_message.source() == source {
+                self.error_message = None;
+            }
+        }
+    }
+
+    // API for Confirmation
+
+    /// Show a confirmation dialog with the message, after replacing the env
+    /// variables it contains, e.g. `$lines`, by their values.
+    pub async fn show_confirmation(&mut self, message: &str) {
+        let message = self.env_variables.lock().await.expand(message);
+        self.confirmation = Some(Confirmation::new(message));
+    }
+
+    pub fn hide_confirmation(&mut self) {
+        self.confirmation = None;
+    }
+
+    // API for Chooser
+
+    /// Show the options (separated by newlines) to choose the value of the
+    /// env variable from.
+    pub fn show_chooser(&mut self, env_variable: EnvVariable, options: String) -> Result<()> {
+        let chooser = Chooser::new(env_variable, options, self.lines.styles.clone())?;
+        self.chooser = Some(chooser);
+        Ok(())
+    }
+
+    pub fn hide_chooser(&mut self) {
+        self.chooser = None;
+    }
+
+    /// Pass a pressed key to the chooser, which aborts if none is displayed.
+    pub fn handle_chooser_key(&mut self, key: &KeyEvent) -> Result<ChooserAction> {
+        match &mut self.chooser {
+            Some(chooser) => chooser.handle_key(key),
+            None => Ok(ChooserAction::Abort),
+        }
     }
 
     // API for Lines
@@ -61,18 +221,106 @@ impl State {
     }
 
     /// Set both the cursor line as well as the selected lines in the UI as
-    /// global environment variables for all future processes.
+    /// global environment variables for all future processes. If a field
+    /// separator is configured, the individual fields of these lines are set
+    /// as well.
     pub async fn add_lines_to_env(&mut self) -> Result<()> {
         // TODO: get_selected_lines is sync and computationally intensive, maybe use spawn_blocking
         if let Some((cursor_line, selected_lines)) = self.get_cursor_line_and_selected_lines() {
+            let lines_count = selected_lines.lines().count().to_string();
             let new_env_variables: EnvVariables = [
                 ("line".parse()?, cursor_line),
                 ("lines".parse()?, selected_lines),
+                ("lines_count".parse()?, lines_count),
             ]
             .into_iter()
             .collect();
             self.set_env(new_env_variables).await;
         };
+        self.add_fields_to_env().await
+    }
+
+    /// Set only the cursor line as env variable, for commands that receive
+    /// the selected lines on stdin instead. The env variables of the selected
+    /// lines and fields are unset, because they might exceed the size limit
+    /// of env variables.
+    pub async fn add_cursor_line_to_env(&mut self) -> Result<()> {
+        self.remove_selected_lines_from_env().await?;
+        if let Some(cursor_line) = self.lines.get_line_under_cursor() {
+            self.set_env([("line".parse()?, cursor_line)].into_iter().collect())
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Unset the env variables of the selected lines and their fields, e.g.
+    /// for commands that are executed once for every selected line instead.
+    pub async fn remove_selected_lines_from_env(&mut self) -> Result<()> {
+        for env_variable in std::mem::take(&mut self.field_env_variables) {
+            self.unset_env(&env_variable).await;
+        }
+        self.unset_env(&"lines".parse()?).await;
+        self.unset_env(&"lines_count".parse()?).await;
+        Ok(())
+    }
+
+    /// The selected lines, or, if none are selected, the cursor line.
+    pub fn get_selected_lines_list(&mut self) -> Vec<String> {
+        match self.get_cursor_line_and_selected_lines() {
+            Some((_, selected_lines)) => selected_lines.split('\n').map(str::to_owned).collect(),
+            None => vec![],
+        }
+    }
+
+    /// The selected lines (or, if none are selected, the cursor line), each
+    /// terminated by the separator, to be written to stdin.
+    pub fn get_selected_lines_input(&mut self, separator: char) -> String {
+        self.get_selected_lines_list()
+            .into_iter()
+            .map(|line| format!("{line}{separator}"))
+            .collect()
+    }
+
+    /// Set the fields of the cursor line as `field<N>` and the newline-joined
+    /// fields of the selected lines as `fields<N>`. If header lines exist,
+    /// the fields are additionally named after the first header line, e.g.
+    /// `field_<header>` and `fields_<header>`.
+    async fn add_fields_to_env(&mut self) -> Result<()> {
+        // Fields of the previous lines might not exist anymore.
+        for env_variable in std::mem::take(&mut self.field_env_variables) {
+            self.unset_env(&env_variable).await;
+        }
+
+        let Some(fields) = self.lines.get_selected_fields() else {
+            return Ok(());
+        };
+
+        let mut new_env_variables = vec![];
+        let mut header_names = HashSet::new();
+        for field in fields {
+            new_env_variables.push((format!("field{}", field.number), field.cursor_line.clone()));
+            new_env_variables.push((
+                format!("fields{}", field.number),
+                field.selected_lines.clone(),
+            ));
+            // Like field selections by name, duplicate header names refer to
+            // the first field with that name.
+            let header = field.header.as_deref().and_then(header_to_env_name);
+            if let Some(header) = header.filter(|header| header_names.insert(header.clone())) {
+                new_env_variables.push((format!("field_{header}"), field.cursor_line));
+                new_env_variables.push((format!("fields_{header}"), field.selected_lines));
+            }
+        }
+
+        let new_env_variables = new_env_variables
+            .into_iter()
+            .map(|(name, value)| Ok((name.parse()?, value)))
+            .collect::<Result<Vec<(EnvVariable, String)>>>()?;
+        self.field_env_variables = new_env_variables
+            .iter()
+            .map(|(env_variable, _)| env_variable.clone())
+            .collect();
+        self.set_env(new_env_variables.into_iter().collect()).await;
         Ok(())
     }
 
@@ -96,6 +344,10 @@ impl State {
         self.lines.unselect_all();
     }
 
+    pub fn toggle_wrap_lines(&mut self) {
+        self.lines.toggle_wrap_lines();
+    }
+
     // API for Help Menu
 
     pub async fn show_help_menu(&mut self) {
@@ -147,6 +399,22 @@ impl State {
         }
     }
 
+    pub fn scroll_left(&mut self, steps: usize) {
+        match self.mode {
+            Mode::Normal => self.lines.scroll_left(steps),
+            // The help menu's text is wrapped, so it can't be scrolled horizontally.
+            Mode::HelpMenu => {}
+        }
+    }
+
+    pub fn scroll_right(&mut self, steps: usize) {
+        match self.mode {
+            Mode::Normal => self.lines.scroll_right(steps),
+            // The help menu's text is wrapped, so it can't be scrolled horizontally.
+            Mode::HelpMenu => {}
+        }
+    }
+
     // API for environment variables
 
     pub fn get_env(&self) -> Arc<Mutex<EnvVariables>> {
@@ -166,4 +434,114 @@ impl State {
     pub async fn read_into_env(&mut self, _env: &EnvVariable) {
         todo!()
     }
+
+    // API for the working directory
+
+    pub fn get_working_dir(&self) -> WorkingDir {
+        self.working_dir.clone()
+    }
+
+    /// Change the working directory of all future commands. The path may
+    /// contain env variables, e.g. `$line`, and be relative to the current
+    /// working directory.
+    pub async fn change_working_dir(&mut self, path: &str) -> Result<()> {
+        let path = self.env_variables.lock().await.expand(path);
+        self.working_dir.change(Path::new(&path))
+    }
+}
+
+/// Convert a header field into a name that can be used in environment
+/// variables, e.g. "READY STATUS" into "ready_status".
+fn header_to_env_name(header: &str) -> Option<String> {
+    let name: String = header
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(header_lines: usize, lines: &str) -> Result<State> {
+        let fields = Fields::separated_by("|");
+        let styles = Styles::parse(None, None, None, None, None, None, None, None, None, None)?;
+        let mut state = State::new(
+            header_lines,
+            0,
+            fields,
+            Columns::default(),
+            0,
+            false,
+            styles,
+            String::new(),
+            EnvVariables::default(),
+            WorkingDir::new(None),
+        );
+        state.update_lines(lines.to_owned())?;
+        Ok(state)
+    }
+
+    #[test]
+    fn test_header_to_env_name() {
+        assert_eq!(header_to_env_name("NAME"), Some("name".to_owned()));
+        assert_eq!(
+            header_to_env_name(" READY STATUS "),
+            Some("ready_status".to_owned())
+        );
+        assert_eq!(header_to_env_name("CPU%"), Some("cpu_".to_owned()));
+        assert_eq!(header_to_env_name("  "), None);
+    }
+
+    #[tokio::test]
+    async fn test_add_lines_to_env() -> Result<()> {
+        let mut state = state(1, "NAME\na\nb\nc")?;
+        let expand = |state: &State| {
+            let env_variables = state.env_variables.try_lock().unwrap();
+            env_variables.expand("$line|$lines|$lines_count")
+        };
+        state.add_lines_to_env().await?;
+        assert_eq!(expand(&state), "a|a|1");
+
+        state.select_all();
+        state.add_lines_to_env().await?;
+        assert_eq!(expand(&state), "a|a\nb\nc|3");
+
+        state.add_cursor_line_to_env().await?;
+        assert_eq!(expand(&state), "a||");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_fields_to_env() -> Result<()> {
+        let mut state = state(1, "NAME|STATUS|Name\na|running|x\nb|stopped|y")?;
+        state.add_lines_to_env().await?;
+        let expand = |state: &State, s: &str| {
+            let env_variables = state.env_variables.try_lock().unwrap();
+            env_variables.expand(s)
+        };
+        assert_eq!(expand(&state, "$field1,$field2,$field3"), "a,running,x");
+        assert_eq!(expand(&state, "$field_name,$field_status"), "a,running");
+
+        state.select();
+        state.lines.move_cursor_down(1);
+        state.select();
+        state.add_lines_to_env().await?;
+        assert_eq!(expand(&state, "$fields2"), "running\nstopped");
+        assert_eq!(expand(&state, "$fields_name"), "a\nb");
+
+        // Fields of previous lines are unset.
+        state.update_lines("NAME\nc".to_owned())?;
+        state.add_lines_to_env().await?;
+        assert_eq!(expand(&state, "$field_name,$field_status,$field2"), "c,,");
+        Ok(())
+    }
 }
@@ -5,9 +5,10 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::backend::CrosstermBackend;
-use std::io::{stdout, Stdout};
+use std::io::{stdout, Write};
 
-pub type Terminal = ratatui::Terminal<CrosstermBackend<Stdout>>;
+/// The output is boxed, so that tests can draw without a real terminal.
+pub type Terminal = ratatui::Terminal<CrosstermBackend<Box<dyn Write>>>;
 
 pub struct TerminalManager {
     pub terminal: Terminal,
@@ -18,13 +19,27 @@ impl TerminalManager {
         enable_raw_mode()?;
         let mut stdout = stdout();
         execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
+        let backend = CrosstermBackend::new(Box::new(stdout) as Box<dyn Write>);
         let mut terminal = Terminal::new(backend)?;
         terminal.hide_cursor()?;
 
         Ok(TerminalManager { terminal })
     }
 
+    /// A terminal of fixed size that discards its output, without entering
+    /// raw mode.
+    #[cfg(test)]
+    pub fn headless() -> Result<Self> {
+        use ratatui::{layout::Rect, TerminalOptions, Viewport};
+
+        let backend = CrosstermBackend::new(Box::new(std::io::sink()) as Box<dyn Write>);
+        let options = TerminalOptions {
+            viewport: Viewport::Fixed(Rect::new(0, 0, 80, 24)),
+        };
+        let terminal = Terminal::with_options(backend, options)?;
+        Ok(TerminalManager { terminal })
+    }
+
     pub fn restore(&mut self) -> Result<()> {
         disable_raw_mode()?;
         execute!(